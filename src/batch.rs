@@ -0,0 +1,35 @@
+use crate::project::{bytes_to_string, DeleteMode, Project};
+
+/// Removes artifacts from every project and prints a report, returns false if anything could not be removed.
+/// Artifacts that are tracked or not ignored by git are skipped
+pub fn delete_all(projects: Vec<Project>, mode: DeleteMode) -> bool {
+    let mut total_freed = 0;
+    let mut success = true;
+    let num_projects = projects.len();
+
+    for mut project in projects {
+        let deletion = project.delete(mode, true);
+        println!(
+            "{:>12}  {} ({})",
+            bytes_to_string(deletion.freed),
+            project.path_string(),
+            project.type_string()
+        );
+        for (path, e) in &deletion.errors {
+            eprintln!("Unable to {} {:?}: {}", mode.verb(), path, e);
+            success = false;
+        }
+        for (path, warning) in &deletion.skipped {
+            eprintln!("Skipped {:?}: {}", path, warning.describe());
+        }
+        total_freed += deletion.freed;
+    }
+
+    let summary = match mode {
+        DeleteMode::Remove => "Reclaimed",
+        DeleteMode::Trash => "Moved to the trash",
+        DeleteMode::Quarantine => "Quarantined",
+    };
+    println!("\n{} {} from {} projects", summary, bytes_to_string(total_freed), num_projects);
+    success
+}
@@ -0,0 +1,207 @@
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Component, Path, PathBuf};
+
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::filter;
+use crate::output::OutputFormat;
+use crate::project::{AgeSource, ProjectType};
+use crate::search::CustomType;
+use crate::PolykillArgs;
+
+pub const LOCAL_CONFIG_FILE: &str = ".polykill.toml";
+
+/// Defaults for command line options, plus settings that can only be set from a config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    dir: Option<String>,
+    verbose: Option<bool>,
+    no_vcs: Option<bool>,
+    #[serde(rename = "type")]
+    types: Option<Vec<String>>,
+    #[serde(rename = "exclude-type")]
+    exclude_types: Option<Vec<String>>,
+    unsorted: Option<bool>,
+    skip_empty: Option<bool>,
+    min_size: Option<String>,
+    older_than: Option<String>,
+    newer_than: Option<String>,
+    age_source: Option<String>,
+    threads: Option<usize>,
+    trash: Option<bool>,
+    quarantine: Option<bool>,
+    yes: Option<bool>,
+    format: Option<String>,
+    dry_run: Option<bool>,
+    /// Paths that are never searched or removed, relative paths are resolved against the config file's directory
+    exclude_paths: Vec<PathBuf>,
+    #[serde(rename = "project-type")]
+    project_types: Vec<ProjectTypeConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectTypeConfig {
+    name: String,
+    markers: Vec<String>,
+    artifacts: Vec<String>,
+}
+
+/// Loads the global config file followed by the local config file in the search directory,
+/// applying their values to any option that wasn't given on the command line
+pub fn load(args: &mut PolykillArgs, matches: &ArgMatches) -> Result<Config, String> {
+    let mut config = match global_config_path() {
+        Some(path) => Config::from_file(&path)?,
+        None => Config::default(),
+    };
+    config.apply(args, matches)?;
+
+    let local_path = Path::new(&args.dir).join(LOCAL_CONFIG_FILE);
+    let local = Config::from_file(&local_path)?;
+    local.check_local(&local_path)?;
+    local.apply(args, matches)?;
+    check_conflicts(args)?;
+
+    config.exclude_paths.extend(local.exclude_paths);
+    Ok(config)
+}
+
+impl Config {
+    fn from_file(path: &Path) -> Result<Config, String> {
+        if !path.is_file() { return Ok(Config::default()); }
+
+        let contents = read_to_string(path)
+            .map_err(|e| format!("Unable to read config file '{}': {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file '{}': {}", path.display(), e))?;
+
+        let config_dir = path.parent().unwrap_or(Path::new("."));
+        config.exclude_paths = config.exclude_paths.iter()
+            .map(|excluded| resolve_path(excluded, config_dir))
+            .collect();
+        Ok(config)
+    }
+
+    fn apply(&self, args: &mut PolykillArgs, matches: &ArgMatches) -> Result<(), String> {
+        set_default(matches, "dir", &mut args.dir, self.dir.clone());
+        set_default(matches, "verbose", &mut args.verbose, self.verbose);
+        set_default(matches, "no_vcs", &mut args.no_vcs, self.no_vcs);
+        set_default(matches, "types", &mut args.types, self.types.as_deref().map(parse_types).transpose()?);
+        set_default(matches, "exclude_types", &mut args.exclude_types, self.exclude_types.as_deref().map(parse_types).transpose()?);
+        set_default(matches, "unsorted", &mut args.unsorted, self.unsorted);
+        set_default(matches, "skip_empty", &mut args.skip_empty, self.skip_empty);
+        set_default(matches, "min_size", &mut args.min_size, self.min_size.as_deref().map(filter::parse_size).transpose()?.map(Some));
+        set_default(matches, "older_than", &mut args.older_than, self.older_than.as_deref().map(filter::parse_days).transpose()?.map(Some));
+        set_default(matches, "newer_than", &mut args.newer_than, self.newer_than.as_deref().map(filter::parse_days).transpose()?.map(Some));
+        set_default(matches, "age_source", &mut args.age_source, self.age_source.as_deref().map(|s| AgeSource::from_str(s, true)).transpose()?);
+        set_default(matches, "threads", &mut args.threads, self.threads.map(Some));
+        set_default(matches, "trash", &mut args.trash, self.trash);
+        set_default(matches, "quarantine", &mut args.quarantine, self.quarantine);
+        set_default(matches, "yes", &mut args.yes, self.yes);
+        set_default(matches, "format", &mut args.format, self.format.as_deref().map(|f| OutputFormat::from_str(f, true)).transpose()?.map(Some));
+        set_default(matches, "dry_run", &mut args.dry_run, self.dry_run);
+        Ok(())
+    }
+
+    /// The local file comes with the directory being searched (e.g. a cloned repository), so it
+    /// can't add project types (which choose what gets removed) or remove anything without asking
+    fn check_local(&self, path: &Path) -> Result<(), String> {
+        let options = [
+            ("dir", self.dir.is_some()),
+            ("yes", self.yes.is_some()),
+            ("dry-run", self.dry_run.is_some()),
+            ("trash", self.trash.is_some()),
+            ("quarantine", self.quarantine.is_some()),
+            ("project-type", !self.project_types.is_empty()),
+        ];
+        match options.iter().find(|(_, is_set)| *is_set) {
+            Some((option, _)) => Err(format!("Invalid config file '{}': '{}' can only be set in the global config file", path.display(), option)),
+            None => Ok(()),
+        }
+    }
+
+    /// Canonical exclude paths, paths that don't exist are dropped
+    pub fn exclude_paths(&self) -> Vec<PathBuf> {
+        self.exclude_paths.iter()
+            .filter_map(|path| path.canonicalize().ok())
+            .collect()
+    }
+
+    pub fn custom_types(&self) -> Result<Vec<CustomType>, String> {
+        let mut custom_types = vec![];
+        for project_type in &self.project_types {
+            if project_type.markers.is_empty() || project_type.artifacts.is_empty() {
+                return Err(format!("Project type '{}' needs at least one marker and one artifact", project_type.name));
+            }
+            let outside = project_type.artifacts.iter().find(|artifact| {
+                let components: Vec<Component> = Path::new(artifact).components().collect();
+                !components.iter().any(|c| matches!(c, Component::Normal(_)))
+                || components.iter().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            });
+            if let Some(artifact) = outside {
+                return Err(format!("Artifact '{}' of project type '{}' must be a relative path inside the project", artifact, project_type.name));
+            }
+            custom_types.push(CustomType {
+                name: Box::leak(project_type.name.clone().into_boxed_str()),
+                markers: project_type.markers.clone(),
+                artifacts: project_type.artifacts.clone(),
+            });
+        }
+        Ok(custom_types)
+    }
+}
+
+/// Options that can't be combined on the command line can't be combined through config files either
+fn check_conflicts(args: &PolykillArgs) -> Result<(), String> {
+    if args.trash && args.quarantine {
+        return Err(String::from("'trash' and 'quarantine' can't be used together"));
+    }
+    if args.yes && args.format.is_some() {
+        return Err(String::from("'yes' and 'format' can't be used together"));
+    }
+    Ok(())
+}
+
+fn set_default<T>(matches: &ArgMatches, id: &str, arg: &mut T, value: Option<T>) {
+    if matches.value_source(id) == Some(ValueSource::CommandLine) { return; }
+    if let Some(value) = value { *arg = value; }
+}
+
+fn parse_types(types: &[String]) -> Result<Vec<ProjectType>, String> {
+    types.iter()
+        .map(|t| ProjectType::from_str(t, true).map_err(|_| format!("invalid project type '{}'", t)))
+        .collect()
+}
+
+fn resolve_path(path: &Path, base: &Path) -> PathBuf {
+    if let (Ok(rest), Some(home)) = (path.strip_prefix("~"), home_dir()) {
+        return home.join(rest);
+    }
+    base.join(path)
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    let home = if cfg!(windows) { env::var_os("USERPROFILE") } else { env::var_os("HOME") };
+    home.map(PathBuf::from)
+}
+
+/// Where application data is stored, $XDG_DATA_HOME or the platform's default
+pub fn data_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ if cfg!(windows) => env::var_os("LOCALAPPDATA").map(PathBuf::from),
+        _ => Some(home_dir()?.join(".local").join("share")),
+    }
+}
+
+fn global_config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+        None => home_dir()?.join(".config"),
+    };
+    Some(config_dir.join("polykill").join("config.toml"))
+}
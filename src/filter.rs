@@ -0,0 +1,64 @@
+use crate::project::Project;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Filters {
+    pub skip_empty: bool,
+    pub min_size: Option<u64>,
+    pub older_than: Option<u64>,
+    pub newer_than: Option<u64>,
+}
+
+impl Filters {
+    /// Size filters only apply once the project has been sized
+    pub fn keeps(&self, project: &Project) -> bool {
+        if project.is_sized() {
+            if self.skip_empty && project.rm_size == 0 { return false; }
+            if self.min_size.is_some_and(|bytes| project.rm_size < bytes) { return false; }
+        }
+        if self.older_than.is_some_and(|days| !project.last_modified.is_some_and(|d| d >= days)) { return false; }
+        if self.newer_than.is_some_and(|days| !project.last_modified.is_some_and(|d| d < days)) { return false; }
+        true
+    }
+
+    pub fn needs_size(&self) -> bool {
+        self.skip_empty || self.min_size.is_some()
+    }
+}
+
+/// Parses durations such as `30d`, `6w`, `3m` or `1y` into a number of days
+pub fn parse_days(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let unit_idx = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(unit_idx);
+
+    let num: u64 = num.parse()
+        .map_err(|_| format!("invalid duration '{}', expected e.g. 30d, 6w, 3m or 1y", s))?;
+    let days_per_unit = match unit.to_ascii_lowercase().as_str() {
+        "" | "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return Err(format!("invalid duration unit '{}', expected one of d, w, m, y", unit)),
+    };
+    num.checked_mul(days_per_unit).ok_or_else(|| format!("duration '{}' is too long", s))
+}
+
+/// Parses sizes such as `500KB`, `100M` or `1.5 GB` into a number of bytes, using the same units as `bytes_to_string`
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    const PREFIXES: &str = "KMGT";
+
+    let s = s.trim();
+    let unit_idx = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (num, unit) = s.split_at(unit_idx);
+
+    let num: f64 = num.parse()
+        .map_err(|_| format!("invalid size '{}', expected e.g. 500KB, 100MB or 1.5GB", s))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let prefix = unit.strip_suffix('B').unwrap_or(&unit);
+    let exponent = match prefix {
+        "" => 0,
+        p if p.len() == 1 && PREFIXES.contains(p) => PREFIXES.find(p).unwrap() as u32 + 1,
+        _ => return Err(format!("invalid size unit '{}', expected one of B, KB, MB, GB, TB", unit)),
+    };
+    Ok((num * 1000_u64.pow(exponent) as f64) as u64)
+}
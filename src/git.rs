@@ -0,0 +1,939 @@
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::env;
+use std::fs::{self, read_to_string, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+
+use crate::config::home_dir;
+use crate::util::{now, SECONDS_PER_DAY};
+
+/// Why removing an artifact of a git repository might lose work
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VcsWarning {
+    /// Files inside the artifact are in the index
+    Tracked,
+    /// The artifact isn't matched by any ignore rule
+    NotIgnored,
+}
+
+impl VcsWarning {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            VcsWarning::Tracked => "contains files tracked by git",
+            VcsWarning::NotIgnored => "is not ignored by git",
+        }
+    }
+}
+
+/// State of the repository a project is in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GitStatus {
+    /// Current branch, or the abbreviated commit id when HEAD is detached
+    pub branch: String,
+    /// Days since the commit HEAD points to, None if there are no commits
+    #[serde(rename = "last_commit_days")]
+    pub last_commit: Option<u64>,
+    /// Whether tracked files have been modified or deleted, changes have been staged, or there are conflicts
+    pub dirty: bool,
+    /// Commits on HEAD that aren't on any remote branch, None if there are no remote branches
+    pub unpushed: Option<usize>,
+}
+
+impl GitStatus {
+    /// Uncommitted and unpushed work, e.g. "dirty, 2 unpushed"
+    pub fn changes(&self) -> String {
+        let mut changes = vec![];
+        if self.dirty { changes.push(String::from("dirty")); }
+        match self.unpushed {
+            Some(0) => {}
+            Some(n) => changes.push(format!("{} unpushed", n)),
+            None => changes.push(String::from("no remote")),
+        }
+        if changes.is_empty() { return String::from("clean"); }
+        changes.join(", ")
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.dirty || self.unpushed != Some(0)
+    }
+}
+
+/// A repository's work tree and the parts of its git directory needed to check artifacts against it
+pub struct Repo {
+    work_tree: PathBuf,
+    git_dir: PathBuf,
+    /// Shared by all worktrees of the repository, holds refs and objects
+    common_dir: PathBuf,
+    index: Vec<IndexEntry>,
+    /// Rules from the global excludes file and info/exclude, in order of precedence
+    exclude_rules: Vec<Rule>,
+    status: OnceLock<GitStatus>,
+}
+
+/// Repositories opened so far by work tree, shared by all projects inside them (e.g. in a monorepo)
+type OpenedRepos = Mutex<HashMap<PathBuf, Arc<OnceLock<Option<Arc<Repo>>>>>>;
+
+impl Repo {
+    /// Opens the repository containing path, if any. Each repository is only read once
+    pub fn find(path: &Path) -> Option<Arc<Repo>> {
+        static OPENED: OnceLock<OpenedRepos> = OnceLock::new();
+
+        let path = path.canonicalize().ok()?;
+        let work_tree = path.ancestors().find(|dir| dir.join(".git").exists())?.to_owned();
+        let repo = OPENED.get_or_init(Default::default).lock().unwrap()
+            .entry(work_tree.to_owned())
+            .or_default()
+            .clone();
+        // other projects in the repository wait for the first one to open it
+        repo.get_or_init(|| Repo::open(work_tree).map(Arc::new)).clone()
+    }
+
+    fn open(work_tree: PathBuf) -> Option<Repo> {
+        let git_dir = resolve_git_dir(&work_tree.join(".git"))?;
+        let common_dir = read_to_string(git_dir.join("commondir"))
+            .map(|dir| git_dir.join(dir.trim()))
+            .unwrap_or_else(|_| git_dir.to_owned());
+
+        let mut exclude_rules = vec![];
+        if let Some(excludes_file) = global_excludes_file() {
+            exclude_rules.extend(read_rules(&excludes_file, ""));
+        }
+        exclude_rules.extend(read_rules(&common_dir.join("info").join("exclude"), ""));
+
+        let index = fs::read(git_dir.join("index")).ok()
+            .and_then(|data| parse_index(&data))
+            .unwrap_or_default();
+        Some(Repo { work_tree, git_dir, common_dir, index, exclude_rules, status: OnceLock::new() })
+    }
+
+    /// Checks an artifact, which has to exist, returns None if it is safe to remove
+    pub fn check(&self, path: &Path) -> Option<VcsWarning> {
+        let path = path.canonicalize().ok()?;
+        let rel_path = relative_path(&path, &self.work_tree)?;
+        if rel_path.is_empty() { return Some(VcsWarning::Tracked); }
+
+        let dir_prefix = format!("{}/", rel_path);
+        if self.index.iter().any(|entry| entry.path == rel_path || entry.path.starts_with(&dir_prefix)) {
+            return Some(VcsWarning::Tracked);
+        }
+        if !self.is_ignored(&rel_path, path.is_dir()) {
+            return Some(VcsWarning::NotIgnored);
+        }
+        None
+    }
+
+    /// The current branch, last commit, and uncommitted and unpushed changes, read on first use
+    pub fn status(&self) -> &GitStatus {
+        self.status.get_or_init(|| self.read_status())
+    }
+
+    fn read_status(&self) -> GitStatus {
+        let head = read_to_string(self.git_dir.join("HEAD")).unwrap_or_default();
+        let head = head.trim();
+        let (branch, head_id) = match head.strip_prefix("ref: ") {
+            Some(name) => (name.strip_prefix("refs/heads/").unwrap_or(name).to_owned(), self.resolve_ref(name)),
+            None => (head.chars().take(7).collect(), parse_id(head)),
+        };
+
+        let objects = Objects::open(&self.common_dir.join("objects"));
+        let head_commit = head_id.and_then(|id| objects.commit(&id));
+        let last_commit = head_commit.as_ref().map(|commit| now().saturating_sub(commit.time) / SECONDS_PER_DAY);
+        let dirty = self.is_dirty() || self.is_staged(&objects, head_commit.as_ref());
+
+        let remote_tips = self.remote_tips();
+        let unpushed = match head_id {
+            _ if remote_tips.is_empty() => None,
+            Some(head_id) => Some(count_unpushed(&objects, head_id, &remote_tips)),
+            None => Some(0),
+        };
+        GitStatus { branch, last_commit, dirty, unpushed }
+    }
+
+    /// Compares the stat data in the index to the work tree, like `git diff-files` does before comparing contents
+    fn is_dirty(&self) -> bool {
+        const GITLINK_MODE: u32 = 0o160000;
+
+        self.index.iter().any(|entry| {
+            if entry.stage != 0 { return true; }
+            if entry.skip_worktree || entry.mode == GITLINK_MODE { return false; }
+
+            let Ok(meta) = self.work_tree.join(&entry.path).symlink_metadata() else { return true };
+            let mtime = meta.modified().ok()
+                .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|time| time.as_secs() as u32);
+            // git only compares whole seconds unless built with USE_NSEC
+            meta.len() as u32 != entry.size || mtime != Some(entry.mtime.0)
+        })
+    }
+
+    /// Compares the index to the tree of the HEAD commit, like `git diff-index --cached` does
+    fn is_staged(&self, objects: &Objects, head: Option<&Commit>) -> bool {
+        let Some(head) = head else { return !self.index.is_empty() };
+        let mut tree = HashMap::new();
+        if !objects.flatten_tree(&head.tree, "", &mut tree) { return false; }
+
+        tree.len() != self.index.len() || self.index.iter().any(|entry| {
+            tree.get(&entry.path) != Some(&(entry.mode, entry.id))
+        })
+    }
+
+    /// Follows a (possibly symbolic) ref to the commit id it points to
+    fn resolve_ref(&self, name: &str) -> Option<ObjectId> {
+        let mut name = name.to_owned();
+        for _ in 0..MAX_SYMREF_DEPTH {
+            let loose = read_to_string(self.git_dir.join(&name))
+                .or_else(|_| read_to_string(self.common_dir.join(&name)));
+            let Ok(contents) = loose else { return self.packed_refs().remove(&name) };
+
+            match contents.trim().strip_prefix("ref: ") {
+                Some(target) => name = target.to_owned(),
+                None => return parse_id(contents.trim()),
+            }
+        }
+        None
+    }
+
+    /// Refs listed in packed-refs, by name
+    fn packed_refs(&self) -> HashMap<String, ObjectId> {
+        let contents = read_to_string(self.common_dir.join("packed-refs")).unwrap_or_default();
+        contents.lines()
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .filter_map(|line| line.split_once(' '))
+            .filter_map(|(id, name)| Some((name.to_owned(), parse_id(id)?)))
+            .collect()
+    }
+
+    /// Commits that remote-tracking branches point to
+    fn remote_tips(&self) -> Vec<ObjectId> {
+        let mut tips: Vec<ObjectId> = self.packed_refs().into_iter()
+            .filter(|(name, _)| name.starts_with("refs/remotes/"))
+            .map(|(_, id)| id)
+            .collect();
+
+        let mut dirs = vec![self.common_dir.join("refs").join("remotes")];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = fs::read_dir(dir) else { continue };
+            for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if let Some(id) = read_to_string(&path).ok().and_then(|contents| parse_id(contents.trim())) {
+                    // symbolic refs such as origin/HEAD point to a branch that is already included
+                    tips.push(id);
+                }
+            }
+        }
+        tips.sort();
+        tips.dedup();
+        tips
+    }
+
+    /// Whether rel_path or any of its parent directories is ignored
+    fn is_ignored(&self, rel_path: &str, is_dir: bool) -> bool {
+        let components: Vec<&str> = rel_path.split('/').collect();
+        let gitignores: Vec<Vec<Rule>> = (0..components.len())
+            .map(|i| {
+                let dir = components[..i].join("/");
+                read_rules(&self.work_tree.join(&dir).join(".gitignore"), &dir)
+            })
+            .collect();
+
+        for (i, name) in components.iter().enumerate() {
+            let path = components[..=i].join("/");
+            let path_is_dir = i + 1 < components.len() || is_dir;
+            let rules = self.exclude_rules.iter().chain(gitignores[..=i].iter().flatten());
+            if ignored_by(rules, &path, name, path_is_dir) { return true; }
+        }
+        false
+    }
+}
+
+type ObjectId = [u8; 20];
+
+const MAX_SYMREF_DEPTH: usize = 5;
+/// Limits how much history is read when looking for unpushed commits
+const MAX_COMMITS_WALKED: usize = 10_000;
+
+/// Counts the commits reachable from head but not from any of the remote tips. Commits are visited
+/// newest first, and again whenever they are reached from another side, to cope with clock skew
+fn count_unpushed(objects: &Objects, head: ObjectId, remote_tips: &[ObjectId]) -> usize {
+    let mut walk = CommitWalk {
+        objects,
+        commits: HashMap::new(),
+        flags: HashMap::new(),
+        queue: BinaryHeap::new(),
+        queued: HashSet::new(),
+        num_queued_head_only: 0,
+        head_only: BTreeSet::new(),
+    };
+    for tip in remote_tips { walk.mark(*tip, FROM_REMOTE); }
+    walk.mark(head, FROM_HEAD);
+
+    for _ in 0..MAX_COMMITS_WALKED {
+        // done once only remote commits are left, and they are older than anything only reached from head
+        let Some(&(newest, _)) = walk.queue.peek() else { break };
+        let oldest_head_only = walk.head_only.first().map_or(u64::MAX, |(time, _)| *time);
+        if walk.num_queued_head_only == 0 && newest < oldest_head_only { break; }
+
+        let (_, id) = walk.queue.pop().unwrap();
+        walk.queued.remove(&id);
+        let flags = walk.flags[&id];
+        if flags == FROM_HEAD { walk.num_queued_head_only -= 1; }
+        for parent in walk.commits[&id].parents.clone() {
+            walk.mark(parent, flags);
+        }
+    }
+    walk.head_only.len()
+}
+
+const FROM_HEAD: u8 = 1;
+const FROM_REMOTE: u8 = 2;
+
+struct CommitWalk<'a> {
+    objects: &'a Objects,
+    commits: HashMap<ObjectId, Commit>,
+    flags: HashMap<ObjectId, u8>,
+    /// Commits whose flags still have to be passed on to their parents, by commit time
+    queue: BinaryHeap<(u64, ObjectId)>,
+    queued: HashSet<ObjectId>,
+    /// Commits in the queue that haven't been reached from a remote tip (yet)
+    num_queued_head_only: usize,
+    /// Commits reached from head but (so far) not from any remote tip, by commit time
+    head_only: BTreeSet<(u64, ObjectId)>,
+}
+
+impl CommitWalk<'_> {
+    fn mark(&mut self, id: ObjectId, flag: u8) {
+        let old_flags = self.flags.get(&id).copied().unwrap_or(0);
+        let flags = old_flags | flag;
+        if flags == old_flags { return; }
+
+        if !self.commits.contains_key(&id) {
+            let Some(commit) = self.objects.commit(&id) else { return };
+            self.commits.insert(id, commit);
+        }
+        let time = self.commits[&id].time;
+        self.flags.insert(id, flags);
+
+        if flags == FROM_HEAD {
+            self.head_only.insert((time, id));
+        } else if old_flags == FROM_HEAD {
+            self.head_only.remove(&(time, id));
+        }
+        if self.queued.insert(id) {
+            self.queue.push((time, id));
+            if flags == FROM_HEAD { self.num_queued_head_only += 1; }
+        } else if old_flags == FROM_HEAD {
+            self.num_queued_head_only -= 1;
+        }
+    }
+}
+
+struct Commit {
+    tree: ObjectId,
+    parents: Vec<ObjectId>,
+    /// Committer timestamp, in seconds since the unix epoch
+    time: u64,
+}
+
+/// Reads objects from the loose object directories and pack files of a repository
+struct Objects {
+    dir: PathBuf,
+    packs: Vec<Pack>,
+}
+
+impl Objects {
+    fn open(dir: &Path) -> Objects {
+        let packs = fs::read_dir(dir.join("pack")).into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+            .filter_map(|path| Pack::open(&path))
+            .collect();
+        Objects { dir: dir.to_owned(), packs }
+    }
+
+    fn commit(&self, id: &ObjectId) -> Option<Commit> {
+        const COMMIT: u8 = 1;
+
+        let (kind, data) = self.read(id)?;
+        if kind != COMMIT { return None; }
+
+        let mut commit = Commit { tree: [0; 20], parents: vec![], time: 0 };
+        for line in String::from_utf8_lossy(&data).lines() {
+            if line.is_empty() { break; }
+            if let Some(tree) = line.strip_prefix("tree ") {
+                commit.tree = parse_id(tree)?;
+            } else if let Some(parent) = line.strip_prefix("parent ") {
+                commit.parents.push(parse_id(parent)?);
+            } else if let Some(committer) = line.strip_prefix("committer ") {
+                // "Name <email> 1700000000 +0000"
+                commit.time = committer.rsplit(' ').nth(1)?.parse().ok()?;
+            }
+        }
+        Some(commit)
+    }
+
+    /// Adds the mode and id of every file below the tree to files, by path. Returns false if the
+    /// tree (or one of its subtrees) couldn't be read
+    fn flatten_tree(&self, id: &ObjectId, prefix: &str, files: &mut HashMap<String, (u32, ObjectId)>) -> bool {
+        const TREE: u8 = 2;
+        const DIR_MODE: u32 = 0o40000;
+
+        let Some((TREE, data)) = self.read(id) else { return false };
+        // entries are "<octal mode> <name>\0<20 byte id>"
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            let Some(space) = rest.iter().position(|&b| b == b' ') else { return false };
+            let Some(nul) = rest.iter().position(|&b| b == 0) else { return false };
+            let Some(entry_id) = rest.get(nul + 1..nul + 21) else { return false };
+            let Ok(mode) = u32::from_str_radix(&String::from_utf8_lossy(&rest[..space]), 8) else { return false };
+            let path = format!("{}{}", prefix, String::from_utf8_lossy(&rest[space + 1..nul]));
+            let entry_id: ObjectId = entry_id.try_into().unwrap();
+            rest = &rest[nul + 21..];
+
+            if mode == DIR_MODE {
+                if !self.flatten_tree(&entry_id, &format!("{}/", path), files) { return false; }
+            } else {
+                files.insert(path, (mode, entry_id));
+            }
+        }
+        true
+    }
+
+    /// The type (as numbered in pack files) and contents of an object
+    fn read(&self, id: &ObjectId) -> Option<(u8, Vec<u8>)> {
+        let hex = to_hex(id);
+        if let Ok(file) = File::open(self.dir.join(&hex[..2]).join(&hex[2..])) {
+            let mut data = vec![];
+            ZlibDecoder::new(file).read_to_end(&mut data).ok()?;
+
+            // "<type> <size>\0<contents>"
+            let header_len = data.iter().position(|&b| b == 0)?;
+            let kind = match data.split(|&b| b == b' ').next()? {
+                b"commit" => 1,
+                b"tree" => 2,
+                b"blob" => 3,
+                b"tag" => 4,
+                _ => return None,
+            };
+            return Some((kind, data.split_off(header_len + 1)));
+        }
+
+        self.packs.iter().find_map(|pack| pack.read(id, self))
+    }
+}
+
+struct Pack {
+    path: PathBuf,
+    /// Contents of the pack's .idx file (version 2)
+    index: Vec<u8>,
+}
+
+impl Pack {
+    fn open(index_path: &Path) -> Option<Pack> {
+        let index = fs::read(index_path).ok()?;
+        if index.get(..4)? != b"\xfftOc" || read_u32(&index, 4)? != 2 { return None; }
+        Some(Pack { path: index_path.with_extension("pack"), index })
+    }
+
+    fn read(&self, id: &ObjectId, objects: &Objects) -> Option<(u8, Vec<u8>)> {
+        self.read_at(self.find(id)?, objects)
+    }
+
+    /// Offset of the object in the pack file, from a binary search of the sorted ids in the index
+    fn find(&self, id: &ObjectId) -> Option<u64> {
+        const FANOUT_START: usize = 8;
+        const IDS_START: usize = FANOUT_START + 256 * 4;
+
+        let fanout = |byte: usize| read_u32(&self.index, FANOUT_START + byte * 4).map(|n| n as usize);
+        let num_objects = fanout(255)?;
+        let mut lo = if id[0] == 0 { 0 } else { fanout(id[0] as usize - 1)? };
+        let mut hi = fanout(id[0] as usize)?;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let mid_id = self.index.get(IDS_START + mid * 20..IDS_START + mid * 20 + 20)?;
+            match mid_id.cmp(&id[..]) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    // ids are followed by crc32s, then 4 byte offsets, then 8 byte offsets for large packs
+                    let offsets_start = IDS_START + num_objects * 24;
+                    let offset = read_u32(&self.index, offsets_start + mid * 4)?;
+                    if offset & 0x8000_0000 == 0 { return Some(offset as u64); }
+
+                    let large_offset_pos = offsets_start + num_objects * 4 + (offset & 0x7fff_ffff) as usize * 8;
+                    return Some(u64::from_be_bytes(self.index.get(large_offset_pos..large_offset_pos + 8)?.try_into().ok()?));
+                }
+            }
+        }
+        None
+    }
+
+    fn read_at(&self, offset: u64, objects: &Objects) -> Option<(u8, Vec<u8>)> {
+        const OFS_DELTA: u8 = 6;
+        const REF_DELTA: u8 = 7;
+
+        let mut file = File::open(&self.path).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut reader = BufReader::new(file);
+
+        let mut byte = read_byte(&mut reader)?;
+        let kind = (byte >> 4) & 0x7;
+        let mut size = (byte & 0x0f) as u64;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_byte(&mut reader)?;
+            size |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+        }
+
+        let base = match kind {
+            OFS_DELTA => {
+                let mut encoded = vec![read_byte(&mut reader)?];
+                while encoded.last()? & 0x80 != 0 { encoded.push(read_byte(&mut reader)?); }
+                let (base_distance, _) = read_varint(&encoded)?;
+                Some(self.read_at(offset.checked_sub(base_distance as u64)?, objects)?)
+            }
+            REF_DELTA => {
+                let mut base_id = [0; 20];
+                reader.read_exact(&mut base_id).ok()?;
+                Some(objects.read(&base_id)?)
+            }
+            _ => None,
+        };
+
+        let mut data = Vec::with_capacity(size as usize);
+        ZlibDecoder::new(reader).take(size).read_to_end(&mut data).ok()?;
+        match base {
+            Some((base_kind, base_data)) => Some((base_kind, apply_delta(&base_data, &data)?)),
+            None => Some((kind, data)),
+        }
+    }
+}
+
+/// Rebuilds an object from its base and a delta of copy and insert instructions
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let read_size = |pos: &mut usize| {
+        let mut size = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *delta.get(*pos)?;
+            *pos += 1;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 { return Some(size); }
+        }
+    };
+
+    let mut pos = 0;
+    let _base_size = read_size(&mut pos)?;
+    let result_size = read_size(&mut pos)?;
+    let mut result = Vec::with_capacity(result_size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            // copy from the base, the low bits say which offset and size bytes are present
+            let mut offset = 0;
+            let mut size = 0;
+            for i in 0..4 {
+                if op & (1 << i) != 0 { offset |= (*delta.get(pos)? as usize) << (8 * i); pos += 1; }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 { size |= (*delta.get(pos)? as usize) << (8 * i); pos += 1; }
+            }
+            if size == 0 { size = 0x10000; }
+            result.extend_from_slice(base.get(offset..offset + size)?);
+        } else if op != 0 {
+            // insert the next op bytes
+            result.extend_from_slice(delta.get(pos..pos + op as usize)?);
+            pos += op as usize;
+        } else {
+            return None;
+        }
+    }
+    Some(result)
+}
+
+fn read_byte(reader: &mut impl Read) -> Option<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte).ok()?;
+    Some(byte[0])
+}
+
+fn parse_id(hex: &str) -> Option<ObjectId> {
+    if hex.len() != 40 { return None; }
+
+    let mut id = [0; 20];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(id)
+}
+
+fn to_hex(id: &ObjectId) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A single pattern from a gitignore file
+struct Rule {
+    pattern: String,
+    /// Directory of the gitignore file relative to the work tree, empty for the root
+    base: String,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole path relative to base, rather than just the file name
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str, base: &str) -> Option<Rule> {
+        let line = line.trim_end_matches([' ', '\r']);
+        if line.is_empty() || line.starts_with('#') { return None; }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() { return None; }
+
+        let anchored = line.contains('/');
+        let pattern = line.strip_prefix('/').unwrap_or(line).to_owned();
+        Some(Rule { pattern, base: base.to_owned(), negated, dir_only, anchored })
+    }
+
+    fn matches(&self, path: &str, name: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir { return false; }
+
+        if !self.anchored { return wildmatch(self.pattern.as_bytes(), name.as_bytes()); }
+        let rel_path = if self.base.is_empty() {
+            path
+        } else {
+            match path.strip_prefix(&self.base).and_then(|rest| rest.strip_prefix('/')) {
+                Some(rel_path) => rel_path,
+                None => return false,
+            }
+        };
+        wildmatch(self.pattern.as_bytes(), rel_path.as_bytes())
+    }
+}
+
+/// Whether the last of the rules matching path ignores it, rules are in order of increasing precedence
+fn ignored_by<'a>(rules: impl DoubleEndedIterator<Item = &'a Rule>, path: &str, name: &str, is_dir: bool) -> bool {
+    rules.rev()
+        .find(|rule| rule.matches(path, name, is_dir))
+        .is_some_and(|rule| !rule.negated)
+}
+
+fn read_rules(path: &Path, base: &str) -> Vec<Rule> {
+    match read_to_string(path) {
+        Ok(contents) => contents.lines().filter_map(|line| Rule::parse(line, base)).collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Matches text against a gitignore glob, '*' and '?' don't match '/' but '**' does
+pub fn wildmatch(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = &pattern[2..];
+            // "**/" also matches no directories at all
+            if rest.first() == Some(&b'/') && wildmatch(&rest[1..], text) { return true; }
+            (0..=text.len()).any(|i| wildmatch(rest, &text[i..]))
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if wildmatch(rest, &text[i..]) { return true; }
+                if text.get(i) == Some(&b'/') { break; }
+            }
+            false
+        }
+        Some(b'?') => !text.is_empty() && text[0] != b'/' && wildmatch(&pattern[1..], &text[1..]),
+        Some(b'[') if pattern.contains(&b']') => {
+            let Some(&c) = text.first() else { return false };
+            let mut class = &pattern[1..];
+            let negated = matches!(class.first(), Some(b'!') | Some(b'^'));
+            if negated { class = &class[1..]; }
+            let end = class.iter().skip(1).position(|&b| b == b']').map(|i| i + 1).unwrap_or(0);
+
+            let mut matched = false;
+            let mut i = 0;
+            while i < end {
+                if i + 2 < end && class[i + 1] == b'-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negated && c != b'/' && wildmatch(&class[end + 1..], &text[1..])
+        }
+        Some(b'\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && wildmatch(&pattern[2..], &text[1..])
+        }
+        Some(&p) => text.first() == Some(&p) && wildmatch(&pattern[1..], &text[1..]),
+    }
+}
+
+/// An entry of the index, with the stat data used to detect changes in the work tree
+struct IndexEntry {
+    /// Relative to the work tree with '/' separators
+    path: String,
+    mtime: (u32, u32),
+    size: u32,
+    mode: u32,
+    id: ObjectId,
+    /// Non-zero for entries with merge conflicts
+    stage: u16,
+    skip_worktree: bool,
+}
+
+/// Entries of a git index file (versions 2 to 4)
+fn parse_index(data: &[u8]) -> Option<Vec<IndexEntry>> {
+    const ENTRY_HEADER_LEN: usize = 62;
+    const EXTENDED_FLAG: u16 = 0x4000;
+    const SKIP_WORKTREE_FLAG: u16 = 0x4000;
+
+    if data.get(..4)? != b"DIRC" { return None; }
+    let version = read_u32(data, 4)?;
+    let num_entries = read_u32(data, 8)?;
+    if !(2..=4).contains(&version) { return None; }
+
+    let mut entries = Vec::with_capacity(num_entries as usize);
+    let mut pos = 12;
+    let mut prev_path: Vec<u8> = vec![];
+    for _ in 0..num_entries {
+        let entry_start = pos;
+        let flags = u16::from_be_bytes(data.get(pos + 60..pos + 62)?.try_into().ok()?);
+        let mut skip_worktree = false;
+        pos += ENTRY_HEADER_LEN;
+        if version >= 3 && flags & EXTENDED_FLAG != 0 {
+            let extended_flags = u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?);
+            skip_worktree = extended_flags & SKIP_WORKTREE_FLAG != 0;
+            pos += 2;
+        }
+
+        let path = if version == 4 {
+            // the path is stored as the number of bytes to drop from the end of the previous path, plus a suffix
+            let (strip, len) = read_varint(data.get(pos..)?)?;
+            pos += len;
+            let suffix_len = data.get(pos..)?.iter().position(|&b| b == 0)?;
+            let mut path = prev_path[..prev_path.len().checked_sub(strip)?].to_vec();
+            path.extend_from_slice(&data[pos..pos + suffix_len]);
+            pos += suffix_len + 1;
+            path
+        } else {
+            let path_len = data.get(pos..)?.iter().position(|&b| b == 0)?;
+            let path = data[pos..pos + path_len].to_vec();
+            // entries are padded with 1-8 nul bytes to a multiple of 8
+            pos = entry_start + (pos - entry_start + path_len + 8) / 8 * 8;
+            path
+        };
+        entries.push(IndexEntry {
+            path: String::from_utf8_lossy(&path).into_owned(),
+            mtime: (read_u32(data, entry_start + 8)?, read_u32(data, entry_start + 12)?),
+            size: read_u32(data, entry_start + 36)?,
+            mode: read_u32(data, entry_start + 24)?,
+            id: data.get(entry_start + 40..entry_start + 60)?.try_into().ok()?,
+            stage: (flags >> 12) & 0x3,
+            skip_worktree,
+        });
+        prev_path = path;
+    }
+    Some(entries)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+/// Git's offset varint encoding, returns the value and the number of bytes read
+fn read_varint(data: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    for (i, &byte) in data.iter().enumerate() {
+        if i > 0 { value = (value + 1) << 7; }
+        value |= (byte & 0x7f) as usize;
+        if byte & 0x80 == 0 { return Some((value, i + 1)); }
+    }
+    None
+}
+
+/// The git directory of a .git directory or a .git file pointing to one (used by worktrees and submodules)
+fn resolve_git_dir(dot_git: &Path) -> Option<PathBuf> {
+    if dot_git.is_dir() { return Some(dot_git.to_owned()); }
+
+    let contents = read_to_string(dot_git).ok()?;
+    let git_dir = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(dot_git.parent()?.join(git_dir))
+}
+
+/// core.excludesFile from the user's git config, or its default location
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = home_dir();
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home.as_ref()?.join(".config"),
+    };
+
+    let config_files = [home.as_ref().map(|home| home.join(".gitconfig")), Some(config_home.join("git").join("config"))];
+    for config in config_files.iter().flatten() {
+        let Ok(contents) = read_to_string(config) else { continue };
+
+        let mut in_core = false;
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_core = line.eq_ignore_ascii_case("[core]");
+            } else if let Some((key, value)) = line.split_once('=') {
+                if !in_core || !key.trim().eq_ignore_ascii_case("excludesfile") { continue; }
+
+                let value = value.trim().trim_matches('"');
+                return match (value.strip_prefix("~/"), &home) {
+                    (Some(rest), Some(home)) => Some(home.join(rest)),
+                    _ => Some(PathBuf::from(value)),
+                };
+            }
+        }
+    }
+    Some(config_home.join("git").join("ignore"))
+}
+
+fn relative_path(path: &Path, base: &Path) -> Option<String> {
+    let rel_path = path.strip_prefix(base).ok()?;
+    let components: Vec<String> = rel_path.components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildmatch_globs() {
+        assert!(wildmatch(b"*.log", b"debug.log"));
+        assert!(!wildmatch(b"*.log", b"logs/debug.log"));
+        assert!(wildmatch(b"**/build", b"build"));
+        assert!(wildmatch(b"**/build", b"a/b/build"));
+        assert!(wildmatch(b"target/**", b"target/debug/app"));
+        assert!(wildmatch(b"a/**/b", b"a/b"));
+        assert!(wildmatch(b"a/**/b", b"a/x/y/b"));
+        assert!(wildmatch(b"dist?", b"dist2"));
+        assert!(!wildmatch(b"dist?", b"dist/"));
+        assert!(wildmatch(b"[bt]arget", b"target"));
+        assert!(!wildmatch(b"[!t]arget", b"target"));
+        assert!(wildmatch(b"v[0-9]", b"v7"));
+        assert!(!wildmatch(b"v[0-9]", b"vx"));
+        assert!(wildmatch(b"\\*literal", b"*literal"));
+        assert!(!wildmatch(b"\\*literal", b"xliteral"));
+    }
+
+    #[test]
+    fn later_rules_take_precedence() {
+        let parse = |lines: &[&str], base: &str| -> Vec<Rule> {
+            lines.iter().filter_map(|line| Rule::parse(line, base)).collect()
+        };
+        let excludes = parse(&["*.tmp", "node_modules"], "");
+        let root = parse(&["# comment", "*.log", "build/", "!keep.log", "/dist", "!*.tmp"], "");
+        let pkg = parse(&["!build/", "local"], "pkg");
+
+        let root_rules = || excludes.iter().chain(&root);
+        assert!(ignored_by(root_rules(), "debug.log", "debug.log", false));
+        assert!(!ignored_by(root_rules(), "keep.log", "keep.log", false));
+        assert!(!ignored_by(root_rules(), "a.tmp", "a.tmp", false));
+        assert!(ignored_by(root_rules(), "node_modules", "node_modules", true));
+        assert!(ignored_by(root_rules(), "build", "build", true));
+        assert!(!ignored_by(root_rules(), "build", "build", false));
+        assert!(ignored_by(root_rules(), "dist", "dist", true));
+        assert!(!ignored_by(root_rules(), "src", "src", true));
+
+        let pkg_rules = || excludes.iter().chain(&root).chain(&pkg);
+        assert!(!ignored_by(pkg_rules(), "pkg/dist", "dist", true));
+        assert!(!ignored_by(pkg_rules(), "pkg/build", "build", true));
+        assert!(ignored_by(pkg_rules(), "pkg/local", "local", false));
+    }
+
+    /// An index entry header with the given size and flags, the name length is added to flags
+    fn index_entry(path: &str, size: u32, flags: u16, extended_flags: Option<u16>) -> Vec<u8> {
+        let mut entry = vec![0; 62];
+        entry[8..12].copy_from_slice(&1_700_000_000u32.to_be_bytes());
+        entry[24..28].copy_from_slice(&0o100644u32.to_be_bytes());
+        entry[36..40].copy_from_slice(&size.to_be_bytes());
+        entry[60..62].copy_from_slice(&(flags | path.len() as u16).to_be_bytes());
+        if let Some(extended_flags) = extended_flags { entry.extend(extended_flags.to_be_bytes()); }
+        entry
+    }
+
+    fn index_file(version: u32, entries: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut data = b"DIRC".to_vec();
+        data.extend(version.to_be_bytes());
+        data.extend((entries.len() as u32).to_be_bytes());
+        for entry in entries { data.extend(entry); }
+        data
+    }
+
+    #[test]
+    fn parse_index_v3() {
+        let padded = |mut entry: Vec<u8>, path: &str| {
+            let len = (entry.len() + path.len() + 8) / 8 * 8;
+            entry.extend(path.as_bytes());
+            entry.resize(len, 0);
+            entry
+        };
+        let data = index_file(3, vec![
+            padded(index_entry("Cargo.toml", 10, 0, None), "Cargo.toml"),
+            padded(index_entry("src/main.rs", 20, 0x4000, Some(0x4000)), "src/main.rs"),
+            padded(index_entry("conflict", 30, 0x2000, None), "conflict"),
+        ]);
+
+        let entries = parse_index(&data).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["Cargo.toml", "src/main.rs", "conflict"]);
+        assert_eq!((entries[0].size, entries[0].mtime.0, entries[0].mode), (10, 1_700_000_000, 0o100644));
+        assert!(!entries[0].skip_worktree && entries[1].skip_worktree);
+        assert_eq!((entries[0].stage, entries[2].stage), (0, 2));
+    }
+
+    #[test]
+    fn parse_index_v4() {
+        let compressed = |mut entry: Vec<u8>, strip: u8, suffix: &str| {
+            entry.push(strip);
+            entry.extend(suffix.as_bytes());
+            entry.push(0);
+            entry
+        };
+        let data = index_file(4, vec![
+            compressed(index_entry("src/a.rs", 1, 0, None), 0, "src/a.rs"),
+            compressed(index_entry("src/b.rs", 2, 0, None), 4, "b.rs"),
+            compressed(index_entry("tests/cli.rs", 3, 0, None), 8, "tests/cli.rs"),
+        ]);
+
+        let entries = parse_index(&data).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["src/a.rs", "src/b.rs", "tests/cli.rs"]);
+        assert_eq!(entries[2].size, 3);
+    }
+
+    #[test]
+    fn parse_index_rejects_invalid_data() {
+        assert!(parse_index(b"not an index").is_none());
+        assert!(parse_index(&index_file(5, vec![])).is_none());
+        // an entry cut off after its header
+        let mut truncated = index_file(2, vec![index_entry("Cargo.toml", 10, 0, None)]);
+        truncated.truncate(truncated.len() - 4);
+        assert!(parse_index(&truncated).is_none());
+    }
+}
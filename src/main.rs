@@ -1,18 +1,32 @@
-use std::{path::Path, cmp::Reverse};
-use clap::Parser;
-use console::Term;
+use std::{path::Path, process};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
+use crate::filter::Filters;
+use crate::output::OutputFormat;
+use crate::project::{AgeSource, DeleteMode, Project, ProjectType};
+use crate::search::SearchOptions;
+
+mod batch;
+mod config;
+mod filter;
+mod git;
 mod menu;
+mod output;
 mod project;
+mod quarantine;
 mod search;
+mod trash;
+mod util;
 
-const ANSI_HIDE_CURSOR: &str = "\x1b[?25l";
-const ANSI_SHOW_CURSOR: &str = "\x1b[?25h";
+const MAX_SEARCH_DEPTH: u32 = 10; // only applies if --no-vcs flag is specified
 
 #[derive(Debug, Parser)]
 #[clap(author, version, verbatim_doc_comment)]
 /// Remove unwanted dependencies and build artifacts from local projects
 pub struct PolykillArgs {
+    #[command(subcommand)]
+    pub command: Option<PolykillCommand>,
+
     #[clap(default_value_t = String::from("."))]
     /// Directory to search for projects
     pub dir: String,
@@ -25,6 +39,14 @@ pub struct PolykillArgs {
     #[arg(long)]
     pub no_vcs: bool,
 
+    /// Only include projects of these types (comma separated)
+    #[arg(short, long = "type", value_name = "TYPES", value_enum, value_delimiter = ',')]
+    pub types: Vec<ProjectType>,
+
+    /// Exclude projects of these types (comma separated)
+    #[arg(short = 'x', long = "exclude-type", value_name = "TYPES", value_enum, value_delimiter = ',')]
+    pub exclude_types: Vec<ProjectType>,
+
     /// Don't sort indexed projects
     #[arg(short, long)]
     pub unsorted: bool,
@@ -33,15 +55,77 @@ pub struct PolykillArgs {
     #[arg(short, long)]
     pub skip_empty: bool,
 
-    /// Don't bring up project menu (for testing purposes only)
+    /// Hide projects with less possible disk savings than this (e.g. 500KB, 100MB, 1GB)
+    #[arg(long, value_name = "SIZE", value_parser = filter::parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Only include projects last modified at least this long ago (e.g. 30d, 6w, 1y)
+    #[arg(long, value_name = "DURATION", value_parser = filter::parse_days)]
+    pub older_than: Option<u64>,
+
+    /// Only include projects last modified less than this long ago (e.g. 30d, 6w, 1y)
+    #[arg(long, value_name = "DURATION", value_parser = filter::parse_days)]
+    pub newer_than: Option<u64>,
+
+    /// What a project's age is based on: newest source file, last commit, newest access time or the project directory
+    #[arg(long, value_enum, value_name = "SOURCE", default_value_t = AgeSource::Source)]
+    pub age_source: AgeSource,
+
+    /// Number of threads used to search for projects and compute their size (defaults to the number of CPUs)
+    #[arg(short = 'j', long, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Move artifacts to the trash instead of deleting them permanently
+    #[arg(long)]
+    pub trash: bool,
+
+    /// Move artifacts to a quarantine directory so deletions can be undone, see the purge command
+    #[arg(long, conflicts_with = "trash")]
+    pub quarantine: bool,
+
+    /// Remove artifacts from all projects without bringing up the menu
+    #[arg(short, long, visible_alias = "all")]
+    pub yes: bool,
+
+    /// Print projects in a machine-readable format instead of bringing up the menu
+    #[arg(long, value_enum, conflicts_with = "yes")]
+    pub format: Option<OutputFormat>,
+
+    /// List artifacts that would be removed without removing anything
     #[arg(long)]
     pub dry_run: bool,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum PolykillCommand {
+    /// Permanently delete quarantined artifacts
+    Purge {
+        /// Only delete artifacts quarantined at least this long ago (e.g. 30d, 6w, 1y)
+        #[arg(long, value_name = "DURATION", value_parser = filter::parse_days, default_value = "0d")]
+        older_than: u64,
+    },
+}
+
 fn main() {
-    const MAX_SEARCH_DEPTH: u32 = 10; // only applies if --no-vcs flag is specified
+    let matches = PolykillArgs::command().get_matches();
+    let mut args = PolykillArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config = config::load(&mut args, &matches).unwrap_or_else(|e| config_error(&e));
+    let custom_types = config.custom_types().unwrap_or_else(|e| config_error(&e));
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
+    }
+
+    if let Some(PolykillCommand::Purge { older_than }) = args.command {
+        match quarantine::purge(older_than) {
+            Ok((freed, purged)) => println!("Purged {} from {} deletions", project::bytes_to_string(freed), purged),
+            Err(e) => {
+                eprintln!("Unable to purge quarantine: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let args = PolykillArgs::parse();
     let path = Path::new(args.dir.as_str());
     if !path.exists() {
         println!("Path '{}' does not exist.", path.display());
@@ -52,42 +136,73 @@ fn main() {
         return;
     }
 
-    if !args.dry_run {
-        let term_height = Term::stdout().size().0 as usize;
-        let top_pad = "\n".repeat(term_height / 2 - 6);
-        let bottom_pad = "\n".repeat(term_height / 2 - 3);
-        print!("{}{}
-        ██████   ██████  ██   ██    ██ ██   ██ ██ ██      ██
-        ██   ██ ██    ██ ██    ██  ██  ██  ██  ██ ██      ██ 
-        ██████  ██    ██ ██     ████   █████   ██ ██      ██        
-        ██      ██    ██ ██      ██    ██  ██  ██ ██      ██   
-        ██       ██████  ███████ ██    ██   ██ ██ ███████ ███████ 
-        v{}
-        
-        searching for projects...{}",
-        ANSI_HIDE_CURSOR, top_pad, env!("CARGO_PKG_VERSION"), bottom_pad
-        );
-    }
+    let mut options = SearchOptions {
+        types: args.types,
+        exclude_types: args.exclude_types,
+        exclude_paths: config.exclude_paths(),
+        custom_types,
+        age_source: args.age_source,
+        on_found: None,
+    };
+    let filters = Filters {
+        skip_empty: args.skip_empty,
+        min_size: args.min_size,
+        older_than: args.older_than,
+        newer_than: args.newer_than,
+    };
+
+    let delete_mode = if args.trash {
+        DeleteMode::Trash
+    } else if args.quarantine {
+        DeleteMode::Quarantine
+    } else {
+        DeleteMode::Remove
+    };
 
-    let mut projects =
-        if args.no_vcs {
-            search::find_projects(path,MAX_SEARCH_DEPTH)
-        } else {
-            search::find_git_projects(path)
-        };
+    if !args.dry_run && !args.yes && args.format.is_none() {
+        let path = path.to_owned();
+        let age_source = options.age_source;
+        let found = menu::project_menu(move |on_found| {
+            options.on_found = Some(on_found);
+            search(&path, args.no_vcs, &options);
+        }, filters, delete_mode, age_source, !args.unsorted, args.verbose);
 
-    if args.skip_empty {
-        projects.retain(|p| p.rm_size > 0);
+        if !found { println!("No projects found."); }
+        return;
     }
-    if projects.is_empty() {
-        println!("{}{}No projects found.", menu::ANSI_CLEAR_SCREEN, ANSI_SHOW_CURSOR);
+
+    let mut projects = search(path, args.no_vcs, &options);
+    projects.retain(|p| filters.keeps(p));
+    if projects.is_empty() && args.format.is_none() {
+        println!("No projects found.");
         return;
     }
 
     if !args.unsorted {
-        projects.sort_unstable_by_key(|p| Reverse(p.rm_size));
-        projects.sort_by_key(|p| p.project_type);
+        projects.sort_by_key(|p| p.sort_key());
     }
     
-    if !args.dry_run { menu::project_menu(projects, args.verbose); }
+    if let Some(format) = args.format {
+        output::print_projects(&projects, format);
+        return;
+    }
+    if args.dry_run {
+        output::print_dry_run(&projects);
+        return;
+    }
+
+    if !batch::delete_all(projects, delete_mode) { process::exit(1); }
+}
+
+fn search(path: &Path, no_vcs: bool, options: &SearchOptions) -> Vec<Project> {
+    if no_vcs {
+        search::find_projects(path, MAX_SEARCH_DEPTH, options)
+    } else {
+        search::find_git_projects(path, options)
+    }
+}
+
+fn config_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
 }
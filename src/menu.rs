@@ -1,9 +1,14 @@
 use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
 use console::{Key, Term};
 
-use crate::project::{Project, ProjectType};
+use crate::filter::Filters;
+use crate::git::GitStatus;
+use crate::project::{bytes_to_string, get_rm_sizes, AgeSource, DeleteMode, Project, ProjectType};
 
-pub const ANSI_CLEAR_SCREEN: &str = "\x1b[H\x1b[J\x1b[H";
+const ANSI_CLEAR_SCREEN: &str = "\x1b[H\x1b[J\x1b[H";
 
 const MIN_PATH_COMPONENTS: usize =  3;
 const PATH_PAD:            usize = 10;
@@ -13,62 +18,39 @@ const PROJECT_TYPE_PAD_SM: usize =  2;
 const LAST_MOD_WIDTH:      usize = 10;
 const RM_SIZE_WIDTH:       usize = 15;
 
-const SMALL_WIN_DIFF: usize = PATH_PAD - PATH_PAD_SM
-                            + PROJECT_TYPE_PAD - PROJECT_TYPE_PAD_SM;
+const LAST_COMMIT_WIDTH:   usize = 14;
+const GIT_PAD:             usize =  3;
+const TRACKED_MARKER:      &str = "  tracked!";
 
-pub fn project_menu(projects: Vec<Project>, verbose: bool) {
-    let [mut max_path_width, mut max_project_type_width] = [0; 2];
-    for p in &projects {
-        let path_len = p.path_string().len();
-        if path_len > max_path_width { max_path_width = path_len }
-        let p_type_len = p.type_string().len();
-        if p_type_len > max_project_type_width { max_project_type_width = p_type_len }
-    }
-
-    let mut truncate_paths = false;
-    let mut path_width = max_path_width + PATH_PAD;
-    let mut p_type_width = max_project_type_width + PROJECT_TYPE_PAD;
-    let row_width = path_width + p_type_width + LAST_MOD_WIDTH + RM_SIZE_WIDTH + 2;
-
-    let screen_width = Term::stdout().size().1 as usize;
-    if row_width > screen_width {
-        if row_width - SMALL_WIN_DIFF > screen_width {
-            truncate_paths = true;
-            max_path_width = (&projects).iter().fold(0, |max, project| {
-                let path_len = project.trunc_path_string(MIN_PATH_COMPONENTS).len();
-                if path_len > max { path_len } else { max }
-            });
-        }
-        path_width = max_path_width + PATH_PAD_SM;
-        p_type_width = max_project_type_width + PROJECT_TYPE_PAD_SM;
-    }
+const DISABLED_TYPE_COLOR: u32 = 240;
 
-    let menu_title = format!(
-        "  {}{}{}{}\n  {}{}{}{}",
-        format_args!("{:<width$}", "Path", width=path_width),
-        format_args!("{:<width$}", "Type", width=p_type_width),
-        format_args!("{:>width$}", "Last Mod.", width=LAST_MOD_WIDTH),
-        format_args!("{:>width$}", "Disk Savings", width=RM_SIZE_WIDTH),
-        format_args!("{:<width$}", "----", width=path_width),
-        format_args!("{:<width$}", "----", width=p_type_width),
-        format_args!("{:>width$}", "----", width=LAST_MOD_WIDTH),
-        format_args!("{:>width$}", "----", width=RM_SIZE_WIDTH),
-    );
-
-    let mut menu_items: Vec<MenuItem> = vec![];
-    for project in projects {
-        let label = create_label(&project, path_width, p_type_width, truncate_paths);
-        let action = MenuAction::Delete(project);
-        let menu_item = MenuItem::new(&label, action);
-        menu_items.push(menu_item);
-    }
+const SMALL_WIN_DIFF: usize = PATH_PAD - PATH_PAD_SM
+                            + PROJECT_TYPE_PAD - PROJECT_TYPE_PAD_SM;
 
-    let mut menu = Menu::new(menu_items, path_width, p_type_width, truncate_paths, verbose);
-    menu.title(&menu_title);
-    menu.show();
+pub type OnFound = Box<dyn Fn(Project) + Send + Sync>;
+
+/// Brings up the project menu straight away, adding projects as the search finds them and inspecting and sizing them
+/// in the background. Returns false if no projects were found
+pub fn project_menu<F>(search: F, filters: Filters, delete_mode: DeleteMode, age_source: AgeSource, sorted: bool, verbose: bool) -> bool
+where
+    F: FnOnce(OnFound) + Send + 'static,
+{
+    let (events, event_rx) = channel();
+
+    let search_events = events.clone();
+    thread::spawn(move || {
+        let found_events = search_events.clone();
+        search(Box::new(move |project| {
+            let _ = found_events.send(MenuEvent::Found(Box::new(project)));
+        }));
+        let _ = search_events.send(MenuEvent::SearchDone);
+    });
+
+    let mut menu = Menu::new(events, filters, delete_mode, age_source, sorted, verbose);
+    menu.run(event_rx)
 }
 
-fn create_label(project: &Project, path_width: usize, p_type_width: usize, truncate_paths: bool) -> String {
+fn create_label(project: &Project, path_width: usize, p_type_width: usize, truncate_paths: bool, git_widths: Option<(usize, usize)>) -> String {
     let disp_path = if truncate_paths {
         project.trunc_path_string(MIN_PATH_COMPONENTS)
     } else {
@@ -81,16 +63,6 @@ fn create_label(project: &Project, path_width: usize, p_type_width: usize, trunc
         String::from("unknown")
     };
 
-    let type_color = match project.project_type {
-        ProjectType::Cargo => 221,
-        ProjectType::Composer => 208,
-        ProjectType::Dotnet => 171,
-        ProjectType::Golang => 81,
-        ProjectType::Gradle => 42,
-        ProjectType::Misc => 147,
-        ProjectType::Mix => 98,
-        ProjectType::Node => 34,
-    };
     let last_mod_color = match project.last_modified {
         Some(days) if days > 180 => 1,
         Some(days) if days > 30 => 3,
@@ -98,20 +70,90 @@ fn create_label(project: &Project, path_width: usize, p_type_width: usize, trunc
         None => 1,
     };
     let rm_size_color = match project.rm_size {
-        size if size > 1000_000_000 => 1,
+        size if size > 1_000_000_000 => 1,
         size if size > 100_000_000 => 3,
         _ => 2,
     };
 
+    let git_columns = match (git_widths, &project.git) {
+        (Some(widths), Some(git)) => format_git_status(git, widths),
+        (Some((branch_width, changes_width)), None) => " ".repeat(branch_width + LAST_COMMIT_WIDTH + changes_width),
+        (None, _) => String::new(),
+    };
+    let tracked = if project.has_tracked() { apply_color256(TRACKED_MARKER, 9) } else { String::new() };
+
     format!(
-        "{}{}{}{}",
+        "{}{}{}{}{}{}",
         pad_right(&disp_path, path_width),
-        apply_color256(&pad_right(&project.type_string(), p_type_width), type_color),
+        format_types(project, p_type_width),
         apply_color256(&pad_left(&last_modified, LAST_MOD_WIDTH), last_mod_color),
         apply_color256(&pad_left(&project.rm_size_str, RM_SIZE_WIDTH), rm_size_color),
+        git_columns,
+        tracked,
     )
 }
 
+/// Branch, last commit and changes columns
+fn format_git_status(git: &GitStatus, (branch_width, changes_width): (usize, usize)) -> String {
+    let last_commit = match git.last_commit {
+        Some(days) => format!("{} days", days),
+        None => String::from("none"),
+    };
+    let changes_color = if git.has_changes() { 1 } else { 2 };
+
+    format!(
+        "{}{}{}",
+        pad_git_column(&git.branch, branch_width),
+        pad_left(&last_commit, LAST_COMMIT_WIDTH),
+        apply_color256(&pad_git_column(&git.changes(), changes_width), changes_color),
+    )
+}
+
+/// Left aligns s in a column that is separated from the previous one by GIT_PAD
+fn pad_git_column(s: &str, width: usize) -> String {
+    pad_left(&pad_right(s, width - GIT_PAD), width)
+}
+
+fn type_color(project_type: ProjectType) -> u32 {
+    match project_type {
+        ProjectType::Cargo => 221,
+        ProjectType::Composer => 208,
+        ProjectType::Cpp => 75,
+        ProjectType::Dotnet => 171,
+        ProjectType::Flutter => 45,
+        ProjectType::Golang => 81,
+        ProjectType::Gradle => 42,
+        ProjectType::Haskell => 97,
+        ProjectType::Maven => 131,
+        ProjectType::Misc => 147,
+        ProjectType::Mix => 98,
+        ProjectType::Node => 34,
+        ProjectType::Ocaml => 214,
+        ProjectType::Python => 39,
+        ProjectType::Ruby => 124,
+        ProjectType::Scala => 160,
+        ProjectType::Swift => 202,
+        ProjectType::Zig => 178,
+        ProjectType::Custom(_) => 250,
+    }
+}
+
+/// Each of the project's types in its own colour, with disabled types greyed out
+fn format_types(project: &Project, width: usize) -> String {
+    if project.project_types.len() == 1 {
+        return apply_color256(&pad_right(&project.type_string(), width), type_color(project.project_type()));
+    }
+
+    let types: Vec<String> = project.project_types.iter()
+        .map(|&project_type| {
+            let color = if project.is_enabled(project_type) { type_color(project_type) } else { DISABLED_TYPE_COLOR };
+            apply_color256(&project_type.to_string(), color)
+        })
+        .collect();
+    let padding = width.saturating_sub(project.type_string().len());
+    format!("{}{}", types.join("+"), " ".repeat(padding))
+}
+
 fn pad_left(s: &str, width: usize) -> String {
     format!("{: >width$}", s, width=width)
 }
@@ -124,6 +166,14 @@ fn apply_color256(input: &str, color: u32) -> String {
     format!("\x1b[38;5;{}m{}\x1b[39m", color, input)
 }
 
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 fn sgr_seq_wrap(s: &str, open: u32, close: u32) -> String {
     format!("\x1b[{}m{}\x1b[{}m", open, s, close)
 }
@@ -133,163 +183,412 @@ pub enum MenuAction {
 }
 
 pub struct MenuItem {
-    pub label: String,
     pub action: MenuAction,
+    pub marked: bool,
 }
 
 impl MenuItem {
-    pub fn new(label: &str, action: MenuAction) -> Self {
+    pub fn new(action: MenuAction) -> Self {
         Self {
-            label: label.to_owned(),
             action,
+            marked: false,
         }
     }
+
+    fn project(&self) -> &Project {
+        let MenuAction::Delete(project) = &self.action;
+        project
+    }
+
+    fn project_mut(&mut self) -> &mut Project {
+        let MenuAction::Delete(project) = &mut self.action;
+        project
+    }
+}
+
+enum MenuEvent {
+    Key(Key),
+    /// A project found by the search, or one that has been inspected (and possibly sized) before being added to the menu
+    Found(Box<Project>),
+    /// Sizes of the artifacts of the project at path
+    Sized(PathBuf, Vec<u64>),
+    SearchDone,
+}
+
+/// Widest path, project type and git status strings of all projects in the menu
+#[derive(Default)]
+struct Layout {
+    max_path_width: usize,
+    max_trunc_path_width: usize,
+    max_p_type_width: usize,
+    /// Zero if no project is in a git repository
+    max_branch_width: usize,
+    max_changes_width: usize,
+}
+
+impl Layout {
+    fn add(&mut self, project: &Project) {
+        self.max_path_width = self.max_path_width.max(project.path_string().len());
+        self.max_trunc_path_width = self.max_trunc_path_width.max(project.trunc_path_string(MIN_PATH_COMPONENTS).len());
+        self.max_p_type_width = self.max_p_type_width.max(project.type_string().len());
+        if let Some(git) = &project.git {
+            self.max_branch_width = self.max_branch_width.max(git.branch.chars().count()).max("Branch".len());
+            self.max_changes_width = self.max_changes_width.max(git.changes().len()).max("Changes".len());
+        }
+    }
+
+    /// Widths of the branch and changes columns, None if they aren't shown
+    fn git_widths(&self) -> Option<(usize, usize)> {
+        if self.max_branch_width == 0 { return None; }
+        Some((self.max_branch_width + GIT_PAD, self.max_changes_width + GIT_PAD))
+    }
+
+    /// Returns the path width, project type width, and whether paths should be truncated
+    fn widths(&self) -> (usize, usize, bool) {
+        let path_width = self.max_path_width + PATH_PAD;
+        let p_type_width = self.max_p_type_width + PROJECT_TYPE_PAD;
+        let git_width = self.git_widths().map_or(0, |(branch_width, changes_width)| branch_width + LAST_COMMIT_WIDTH + changes_width);
+        let row_width = path_width + p_type_width + LAST_MOD_WIDTH + RM_SIZE_WIDTH + git_width + TRACKED_MARKER.len() + 3;
+
+        let screen_width = Term::stdout().size().1 as usize;
+        if row_width <= screen_width { return (path_width, p_type_width, false); }
+
+        let truncate_paths = row_width - SMALL_WIN_DIFF > screen_width;
+        let max_path_width = if truncate_paths { self.max_trunc_path_width } else { self.max_path_width };
+        (max_path_width + PATH_PAD_SM, self.max_p_type_width + PROJECT_TYPE_PAD_SM, truncate_paths)
+    }
 }
 
 pub struct Menu {
-    title: Option<String>,
     items: Vec<MenuItem>,
     selected_item: usize,
-    selected_page: usize,
     items_per_page: usize,
-    num_pages: usize,
-    page_start: usize,
-    page_end: usize,
     verbose: bool,
     message: Option<String>,
-    path_width: usize,
-    p_type_width: usize,
-    truncate_paths: bool
+    confirm_marked: bool,
+    layout: Layout,
+    filters: Filters,
+    delete_mode: DeleteMode,
+    age_source: AgeSource,
+    sorted: bool,
+    searching: bool,
+    /// Projects being inspected or sized in the background
+    num_sizing: usize,
+    events: Sender<MenuEvent>,
+    key_ack: Option<Sender<bool>>,
+    /// Paths of quarantined projects with the quarantine ids needed to restore them, most recent last
+    undo_stack: Vec<(PathBuf, String)>,
 }
 
 impl Menu {
-    pub fn new(items: Vec<MenuItem>,
-               path_width: usize,
-               p_type_width: usize,
-               truncate_paths: bool,
-               verbose: bool) -> Self {
-
+    fn new(events: Sender<MenuEvent>, filters: Filters, delete_mode: DeleteMode, age_source: AgeSource, sorted: bool, verbose: bool) -> Self {
         let mut items_per_page = if verbose {
             Term::stdout().size().0 as i32 - 9
         } else {
             Term::stdout().size().0 as i32 - 6
         };
         if items_per_page < 1 { items_per_page = 1 }
-        let items_per_page = items_per_page as usize;
-        let num_pages = ((items.len() - 1) / items_per_page) + 1;
 
-        let mut menu = Self {
-            title: None,
-            items,
+        Self {
+            items: vec![],
             selected_item: 0,
-            selected_page: 0,
-            items_per_page,
-            num_pages,
-            page_start: 0,
-            page_end: 0,
+            items_per_page: items_per_page as usize,
             verbose,
             message: None,
-            path_width,
-            p_type_width,
-            truncate_paths
-        };
-        menu.set_page(0);
-        menu
-    }
-
-    pub fn title(&mut self, title: &str) {
-        self.title = Some(title.to_owned());
+            confirm_marked: false,
+            layout: Layout::default(),
+            filters,
+            delete_mode,
+            age_source,
+            sorted,
+            searching: true,
+            num_sizing: 0,
+            events,
+            key_ack: None,
+            undo_stack: vec![],
+        }
     }
 
-    pub fn show(&mut self) {
+    fn run(&mut self, event_rx: Receiver<MenuEvent>) -> bool {
         let mut stdout = Term::buffered_stdout();
-
         stdout.hide_cursor().unwrap();
-
         self.draw(&mut stdout);
-        self.run_navigation(&mut stdout);
-    }
 
-    fn run_navigation(&mut self, stdout: &mut Term) {
-        loop {
-            let key = stdout.read_key().unwrap();
-
-            match key {
-                Key::ArrowUp | Key::Char('k') => {
-                    if self.selected_item != self.page_start {
-                        self.selected_item -= 1;
-                    } else if self.selected_page != 0 {
-                        self.set_page(self.selected_page - 1);
-                        self.selected_item = self.page_end;
+        while let Ok(event) = event_rx.recv() {
+            let mut resort = false;
+            let mut event = Some(event);
+            while let Some(next) = event {
+                match next {
+                    MenuEvent::Key(key) => {
+                        let keep_open = self.handle_key(key, &mut stdout);
+                        if let Some(key_ack) = &self.key_ack { let _ = key_ack.send(keep_open); }
+                        if !keep_open {
+                            self.exit(&mut stdout);
+                            return true;
+                        }
                     }
+                    MenuEvent::Found(project) => resort |= self.add_project(*project),
+                    MenuEvent::Sized(path, rm_sizes) => resort |= self.set_sizes(path, rm_sizes),
+                    MenuEvent::SearchDone => self.searching = false,
                 }
-                Key::ArrowDown | Key::Char('j') => {
-                    if self.selected_item < self.page_end {
-                        self.selected_item += 1
-                    } else if self.selected_page < self.num_pages - 1 {
-                        self.set_page(self.selected_page + 1);
-                    }
-                }
-                Key::ArrowLeft | Key::Char('h') => {
-                    if self.selected_page != 0 {
-                        self.set_page(self.selected_page - 1);
-                    }
-                }
-                Key::ArrowRight | Key::Char('l') => {
-                    if self.selected_page < self.num_pages - 1 {
-                        self.set_page(self.selected_page + 1);
-                    }
-                }
-                Key::Escape | Key::Char('q') => {
-                    self.exit(stdout);
-                    break;
-                }
-                Key::Enter | Key::Del => {
-                    self.set_working(stdout);
-                    self.run_action(self.selected_item);
-                }
-                _ => {}
+                event = event_rx.try_recv().ok();
             }
 
-            self.draw(stdout);
+            if resort && self.sorted { self.sort_items(); }
+            if !self.searching && self.num_sizing == 0 && self.items.is_empty() {
+                self.exit(&mut stdout);
+                return false;
+            }
+            self.draw(&mut stdout);
         }
+        true
     }
 
-    fn set_page(&mut self, page: usize) {
-        self.selected_page = page;
-        self.page_start = self.selected_page * self.items_per_page;
-        self.selected_item = self.page_start;
-        if self.items.len() > self.page_start + self.items_per_page {
-            self.page_end = self.page_start + self.items_per_page - 1
-        } else {
-            self.page_end = self.items.len() - 1
+    /// Handles a key press, returns false if the menu should be closed
+    fn handle_key(&mut self, key: Key, stdout: &mut Term) -> bool {
+        if self.confirm_marked {
+            self.confirm_marked = false;
+            if let Key::Char('y') | Key::Char('Y') = key {
+                self.run_marked_actions(stdout);
+            }
+            return true;
         }
+
+        let page = self.selected_page();
+        let last_item = self.items.len() - 1;
+        match key {
+            Key::ArrowUp | Key::Char('k') if self.selected_item != 0 => {
+                self.selected_item -= 1;
+            }
+            Key::ArrowDown | Key::Char('j') if self.selected_item < last_item => {
+                self.selected_item += 1;
+            }
+            Key::ArrowLeft | Key::Char('h') if page != 0 => {
+                self.selected_item = (page - 1) * self.items_per_page;
+            }
+            Key::ArrowRight | Key::Char('l') if page < self.num_pages() - 1 => {
+                self.selected_item = (page + 1) * self.items_per_page;
+            }
+            Key::Escape | Key::Char('q') => return false,
+            Key::Char(' ') => {
+                self.items[self.selected_item].marked ^= true;
+            }
+            Key::Char('a') => {
+                let (page_start, page_end) = self.page_bounds();
+                self.toggle_marks(page_start, page_end);
+            }
+            Key::Char('A') => {
+                self.toggle_marks(0, last_item);
+            }
+            Key::Char('i') => {
+                for item in &mut self.items { item.marked ^= true; }
+            }
+            Key::Char('u') => {
+                self.undo();
+            }
+            Key::Char(c @ '1'..='9') => {
+                self.toggle_type(c as usize - '1' as usize);
+            }
+            Key::Enter | Key::Del if self.items.iter().any(|item| item.marked) => {
+                self.confirm_marked = true;
+            }
+            Key::Enter | Key::Del => {
+                self.set_working(self.selected_item);
+                self.draw(stdout);
+                self.run_action(self.selected_item, false);
+            }
+            _ => {}
+        }
+        true
     }
 
-    fn set_working(&mut self, stdout: &mut Term) {
-        let MenuAction::Delete(project) = &mut self.items[self.selected_item].action;
-        project.rm_size_str = String::from("working...");
-        self.items[self.selected_item].label = create_label(project, self.path_width, self.p_type_width, self.truncate_paths);
-        self.draw(stdout);
+    /// Returns true if the menu needs to be resorted
+    fn add_project(&mut self, mut project: Project) -> bool {
+        let events = self.events.clone();
+        if !project.is_inspected() {
+            self.num_sizing += 1;
+            // the project is only shown once it's known which of its artifacts are safe to remove
+            let (age_source, needs_size) = (self.age_source, self.filters.needs_size());
+            rayon::spawn(move || {
+                project.inspect(age_source);
+                // and once the size filters can be applied to it
+                if needs_size { project.compute_size(); }
+                let _ = events.send(MenuEvent::Found(Box::new(project)));
+            });
+            return false;
+        }
+
+        self.num_sizing -= 1;
+        if !self.filters.keeps(&project) { return false; }
+        if project.is_sized() {
+            self.push_item(project);
+            return true;
+        }
+
+        self.num_sizing += 1;
+        let path = project.path.to_owned();
+        let rm_paths = project.rm_paths.to_owned();
+        rayon::spawn(move || {
+            let _ = events.send(MenuEvent::Sized(path, get_rm_sizes(&rm_paths)));
+        });
+        self.push_item(project);
+        false
+    }
+
+    fn push_item(&mut self, project: Project) {
+        self.layout.add(&project);
+        self.items.push(MenuItem::new(MenuAction::Delete(project)));
+        if self.key_ack.is_none() { self.start_key_reader(); }
+    }
+
+    /// Returns true if the menu needs to be resorted
+    fn set_sizes(&mut self, path: PathBuf, rm_sizes: Vec<u64>) -> bool {
+        self.num_sizing -= 1;
+        let Some(idx) = self.items.iter().position(|item| item.project().path == path) else { return false };
+
+        let project = self.items[idx].project_mut();
+        if project.is_sized() { return false; }
+        project.set_rm_sizes(rm_sizes);
+        true
+    }
+
+    /// Reads keys on a separate thread so the menu can keep updating while waiting for input.
+    /// The reader waits for each key to be handled before reading the next one, so that it
+    /// isn't left waiting for input (with the terminal in raw mode) when the menu closes
+    fn start_key_reader(&mut self) {
+        let (key_ack, ack_rx) = channel();
+        let events = self.events.clone();
+        thread::spawn(move || {
+            let term = Term::stdout();
+            while let Ok(key) = term.read_key() {
+                if events.send(MenuEvent::Key(key)).is_err() || ack_rx.recv() != Ok(true) { break; }
+            }
+        });
+        self.key_ack = Some(key_ack);
+    }
+
+    fn sort_items(&mut self) {
+        let selected_path = self.items[self.selected_item].project().path.to_owned();
+        self.items.sort_by_key(|item| item.project().sort_key());
+        self.selected_item = self.items.iter()
+            .position(|item| item.project().path == selected_path)
+            .unwrap();
+    }
+
+    fn selected_page(&self) -> usize {
+        self.selected_item / self.items_per_page
+    }
+
+    fn num_pages(&self) -> usize {
+        ((self.items.len().max(1) - 1) / self.items_per_page) + 1
+    }
+
+    fn page_bounds(&self) -> (usize, usize) {
+        let page_start = self.selected_page() * self.items_per_page;
+        let page_end = (page_start + self.items_per_page).min(self.items.len()) - 1;
+        (page_start, page_end)
+    }
+
+    /// Marks every item in the range, or unmarks them if they are all marked already
+    fn toggle_marks(&mut self, start: usize, end: usize) {
+        let items = &mut self.items[start..=end];
+        let mark = !items.iter().all(|item| item.marked);
+        for item in items { item.marked = mark; }
+    }
+
+    /// Enables or disables the nth type of the selected project
+    fn toggle_type(&mut self, type_idx: usize) {
+        let project = self.items[self.selected_item].project_mut();
+        let Some(&project_type) = project.project_types.get(type_idx) else { return };
+
+        if project.toggle_type(project_type) && self.sorted { self.sort_items(); }
+    }
+
+    fn set_working(&mut self, item_idx: usize) {
+        self.items[item_idx].project_mut().rm_size_str = String::from("working...");
     }
 
     fn draw(&self, stdout: &mut Term) {
-        stdout.write(ANSI_CLEAR_SCREEN.as_bytes()).unwrap();
+        stdout.write_all(ANSI_CLEAR_SCREEN.as_bytes()).unwrap();
 
-        if let Some(title) = &self.title {
-            let controls_str = "  ↓,↑,←,→: select project |  enter: delete artifacts |  q: quit\n";
-            stdout.write_line(&sgr_seq_wrap(controls_str, 2, 22)).unwrap();
-            stdout.write_line(&sgr_seq_wrap(title, 1, 22)).unwrap();
+        if self.items.is_empty() {
+            draw_splash(stdout);
+            stdout.flush().unwrap();
+            return;
         }
 
-        for (i, item) in self.items[self.page_start..=self.page_end].iter().enumerate() {
-            if self.page_start + i == self.selected_item {
-                stdout.write_line(&sgr_seq_wrap(&format!("> {}", item.label), 1, 22)).unwrap();
+        let (path_width, p_type_width, truncate_paths) = self.layout.widths();
+        let git_widths = self.layout.git_widths();
+        let toggle_str = if self.items[self.selected_item].project().project_types.len() > 1 { "  1-9: toggle type |" } else { "" };
+        let undo_str = if self.delete_mode == DeleteMode::Quarantine { "  u: undo |" } else { "" };
+        let controls_str = format!(
+            "  ↓,↑,←,→: select project |  space: mark |  a/A: mark page/all |  i: invert marks |{}  enter: {} artifacts |{}  q: quit\n",
+            toggle_str, self.delete_mode.verb(), undo_str
+        );
+        stdout.write_line(&sgr_seq_wrap(&controls_str, 2, 22)).unwrap();
+        let git_title = |branch: &str, last_commit: &str, changes: &str| match git_widths {
+            Some((branch_width, changes_width)) => format!(
+                "{}{}{}",
+                pad_git_column(branch, branch_width),
+                pad_left(last_commit, LAST_COMMIT_WIDTH),
+                pad_git_column(changes, changes_width),
+            ),
+            None => String::new(),
+        };
+        let title = format!(
+            "   {}{}{}{}{}\n   {}{}{}{}{}",
+            format_args!("{:<width$}", "Path", width=path_width),
+            format_args!("{:<width$}", "Type", width=p_type_width),
+            format_args!("{:>width$}", "Last Mod.", width=LAST_MOD_WIDTH),
+            format_args!("{:>width$}", "Disk Savings", width=RM_SIZE_WIDTH),
+            git_title("Branch", "Last Commit", "Changes"),
+            format_args!("{:<width$}", "----", width=path_width),
+            format_args!("{:<width$}", "----", width=p_type_width),
+            format_args!("{:>width$}", "----", width=LAST_MOD_WIDTH),
+            format_args!("{:>width$}", "----", width=RM_SIZE_WIDTH),
+            git_title("----", "----", "----"),
+        );
+        stdout.write_line(&sgr_seq_wrap(&title, 1, 22)).unwrap();
+
+        let (page_start, page_end) = self.page_bounds();
+        for (i, item) in self.items[page_start..=page_end].iter().enumerate() {
+            let mark = if item.marked { '*' } else { ' ' };
+            let label = create_label(item.project(), path_width, p_type_width, truncate_paths, git_widths);
+            if page_start + i == self.selected_item {
+                stdout.write_line(&sgr_seq_wrap(&format!(">{} {}", mark, label), 1, 22)).unwrap();
             } else {
-                stdout.write_line(&format!("  {}", item.label)).unwrap();
+                stdout.write_line(&format!(" {} {}", mark, label)).unwrap();
             }
         }
-        stdout.write_line(&format!("Page {} of {}", self.selected_page + 1, self.num_pages)).unwrap();
+
+        let (num_marked, marked_size) = self.items.iter()
+            .filter(|item| item.marked)
+            .fold((0, 0), |(n, size), item| (n + 1, size + item.project().rm_size));
+        if self.confirm_marked {
+            let num_unsafe: usize = self.items.iter()
+                .filter(|item| item.marked)
+                .map(|item| item.project().vcs_warnings.len())
+                .sum();
+            let mut prompt = format!(
+                "{} artifacts from {} projects ({})? [y/N]",
+                capitalize(self.delete_mode.verb()), num_marked, bytes_to_string(marked_size)
+            );
+            if num_unsafe > 0 {
+                prompt += &format!(" ({} artifacts not ignored by git are skipped)", num_unsafe);
+            }
+            stdout.write_line(&sgr_seq_wrap(&apply_color256(&prompt, 9), 1, 22)).unwrap();
+        } else {
+            let mut footer = format!("Page {} of {}", self.selected_page() + 1, self.num_pages());
+            if num_marked > 0 {
+                footer += &format!(" | selected: {} projects, {}", num_marked, bytes_to_string(marked_size));
+            }
+            if self.searching {
+                footer += " | searching...";
+            } else if self.num_sizing > 0 {
+                footer += &format!(" | sizing {} projects...", self.num_sizing);
+            }
+            stdout.write_line(&footer).unwrap();
+        }
 
         if let Some(message) = &self.message {
             stdout.write_line(&apply_color256(&format!("\n{}", message), 9)).unwrap();
@@ -299,19 +598,74 @@ impl Menu {
     }
 
     fn exit(&self, stdout: &mut Term) {
-        stdout.write(ANSI_CLEAR_SCREEN.as_bytes()).unwrap();
+        stdout.write_all(ANSI_CLEAR_SCREEN.as_bytes()).unwrap();
         stdout.show_cursor().unwrap();
         stdout.flush().unwrap();
     }
 
-    fn run_action(&mut self, action_idx: usize) {
+    fn run_marked_actions(&mut self, stdout: &mut Term) {
+        let marked: Vec<usize> = (0..self.items.len())
+            .filter(|&i| self.items[i].marked)
+            .collect();
+        for &i in &marked { self.set_working(i); }
+        self.draw(stdout);
+
+        let mut messages = vec![];
+        for i in marked {
+            self.run_action(i, true);
+            self.items[i].marked = false;
+            if let Some(message) = self.message.take() { messages.push(message); }
+        }
+        if !messages.is_empty() { self.message = Some(messages.join("\n")); }
+    }
+
+    /// Runs the action of a single item, skip_unsafe leaves artifacts git doesn't consider safe to
+    /// remove alone (for bulk deletes, where they aren't looked at one by one)
+    fn run_action(&mut self, action_idx: usize, skip_unsafe: bool) {
         let action = &mut self.items[action_idx].action;
         match action {
             MenuAction::Delete(project) => {
-                let message = project.delete();
-                if self.verbose { self.message = message; }
-                self.items[action_idx].label = create_label(project, self.path_width, self.p_type_width, self.truncate_paths);
+                let deletion = project.delete(self.delete_mode, skip_unsafe);
+                if self.verbose {
+                    self.message = deletion.message();
+                } else if !deletion.skipped.is_empty() {
+                    self.message = Some(deletion.skipped_lines().join("\n"));
+                }
+                if let Some(id) = deletion.quarantine_id {
+                    self.undo_stack.push((project.path.to_owned(), id));
+                }
             }
         }
     }
+
+    /// Restores the most recently quarantined project
+    fn undo(&mut self) {
+        let Some((path, id)) = self.undo_stack.pop() else { return };
+        let Some(item) = self.items.iter_mut().find(|item| item.project().path == path) else { return };
+
+        let message = match item.project_mut().restore(&id) {
+            Ok(restored) => restored.iter()
+                .map(|path| format!("Restored {:?}", path))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Err(e) => format!("Unable to restore {:?}: {}", path, e),
+        };
+        if self.verbose { self.message = Some(message); }
+    }
+}
+
+fn draw_splash(stdout: &mut Term) {
+    let term_height = Term::stdout().size().0 as usize;
+    let top_pad = "\n".repeat((term_height / 2).saturating_sub(6));
+    write!(stdout, "{}
+        ██████   ██████  ██   ██    ██ ██   ██ ██ ██      ██
+        ██   ██ ██    ██ ██    ██  ██  ██  ██  ██ ██      ██ 
+        ██████  ██    ██ ██     ████   █████   ██ ██      ██        
+        ██      ██    ██ ██      ██    ██  ██  ██ ██      ██   
+        ██       ██████  ███████ ██    ██   ██ ██ ███████ ███████ 
+        v{}
+        
+        searching for projects...",
+        top_pad, env!("CARGO_PKG_VERSION")
+    ).unwrap();
 }
@@ -0,0 +1,116 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::git::{GitStatus, VcsWarning};
+use crate::project::{bytes_to_string, Project};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A single JSON array of projects
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+pub fn print_projects(projects: &[Project], format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let objects: Vec<String> = projects.iter()
+                .map(|p| format!("  {}", project_to_json(p)))
+                .collect();
+            if objects.is_empty() {
+                println!("[]");
+            } else {
+                println!("[\n{}\n]", objects.join(",\n"));
+            }
+        }
+        OutputFormat::Ndjson => {
+            for project in projects {
+                println!("{}", project_to_json(project));
+            }
+        }
+    }
+}
+
+pub fn print_dry_run(projects: &[Project]) {
+    let mut rows: Vec<(String, String)> = vec![];
+    let mut total_size = 0;
+    for project in projects {
+        let git = match &project.git {
+            Some(git) => format!(" [{}, last commit {}, {}]", git.branch, last_commit_string(git.last_commit), git.changes()),
+            None => String::new(),
+        };
+        rows.push((
+            format!("{} ({}){}", project.path_string(), project.type_string(), git),
+            project.rm_size_str.to_owned(),
+        ));
+        for (path, size) in project.rm_paths.iter().zip(&project.rm_sizes) {
+            if path.symlink_metadata().is_err() { continue; }
+            let label = match project.vcs_warning(path) {
+                Some(VcsWarning::Tracked) => format!("    {} (tracked!)", path.display()),
+                Some(VcsWarning::NotIgnored) => format!("    {} (not ignored)", path.display()),
+                None => format!("    {}", path.display()),
+            };
+            rows.push((label, bytes_to_string(*size)));
+        }
+        total_size += project.rm_size;
+    }
+
+    let label_width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    for (label, size) in rows {
+        println!("{:<label_width$}  {:>10}", label, size, label_width=label_width);
+    }
+    println!("\nWould reclaim {} from {} projects", bytes_to_string(total_size), projects.len());
+}
+
+/// A project as it is printed by `--format`
+#[derive(Serialize)]
+struct ProjectJson<'a> {
+    path: String,
+    /// The main type
+    #[serde(rename = "type")]
+    project_type: String,
+    types: Vec<TypeJson>,
+    rm_paths: Vec<RmPathJson>,
+    rm_size: u64,
+    last_modified_days: Option<u64>,
+    git: Option<&'a GitStatus>,
+}
+
+#[derive(Serialize)]
+struct TypeJson {
+    name: String,
+    /// Whether artifacts of this type are removed
+    enabled: bool,
+}
+
+#[derive(Serialize)]
+struct RmPathJson {
+    path: String,
+    size: u64,
+}
+
+fn project_to_json(project: &Project) -> String {
+    let json = ProjectJson {
+        path: project.path_string(),
+        project_type: project.project_type().to_string(),
+        types: project.project_types.iter()
+            .map(|t| TypeJson { name: t.to_string(), enabled: project.is_enabled(*t) })
+            .collect(),
+        rm_paths: project.rm_paths.iter()
+            .zip(&project.rm_sizes)
+            .map(|(path, size)| RmPathJson { path: path.display().to_string(), size: *size })
+            .collect(),
+        rm_size: project.rm_size,
+        last_modified_days: project.last_modified,
+        git: project.git.as_ref(),
+    };
+    serde_json::to_string(&json).unwrap()
+}
+
+fn last_commit_string(last_commit: Option<u64>) -> String {
+    match last_commit {
+        Some(days) => format!("{} days ago", days),
+        None => String::from("none"),
+    }
+}
@@ -1,37 +1,71 @@
+use std::cmp::Reverse;
 use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
-use std::fs::{metadata, read_dir, remove_dir_all, remove_file, ReadDir};
+use std::fs::{metadata, read_dir, read_to_string, remove_dir_all, remove_file, Metadata, ReadDir};
 use std::io;
-use std::path::PathBuf;
-use std::time::{Duration, SystemTime};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use clap::ValueEnum;
+use rayon::prelude::*;
+
+use crate::git::{self, GitStatus, VcsWarning};
+use crate::search::is_project_dir;
+use crate::util::days_since;
+use crate::{quarantine, trash};
 
 #[derive(Debug)]
 pub struct Project {
     pub path: PathBuf,
-    pub project_type: ProjectType,
+    /// Every type the project was detected as, the first one is its main type
+    pub project_types: Vec<ProjectType>,
     pub rm_paths: Vec<PathBuf>,
+    /// Type each of the rm_paths belongs to
+    pub rm_types: Vec<ProjectType>,
+    /// Types whose artifacts are left alone when deleting, and not counted in rm_size
+    pub disabled_types: Vec<ProjectType>,
+    /// Artifacts that git doesn't consider safe to remove, see `check_vcs`
+    pub vcs_warnings: Vec<(PathBuf, VcsWarning)>,
+    /// State of the git repository containing the project, if any
+    pub git: Option<GitStatus>,
+    pub rm_sizes: Vec<u64>,
     pub rm_size: u64,
     pub rm_size_str: String,
+    /// Days since the project was last modified, according to `age_source`
     pub last_modified: Option<u64>,
+    pub age_source: AgeSource,
+    /// Whether the git status and age have been read, see `inspect`
+    inspected: bool,
 }
 
 impl Project {
+    /// Creates a project without computing its size, see `compute_size`
     pub fn new(path: PathBuf, project_type: ProjectType, rm_paths: Vec<PathBuf>) -> Project {
-        let rm_size = get_rm_size(&rm_paths);
-        let rm_size_str = bytes_to_string(rm_size);
         let last_modified = get_time_since_last_mod(&path);
         Project {
             path,
-            project_type,
+            project_types: vec![project_type],
+            rm_types: vec![project_type; rm_paths.len()],
             rm_paths,
-            rm_size,
-            rm_size_str,
+            disabled_types: Vec::new(),
+            vcs_warnings: Vec::new(),
+            git: None,
+            rm_sizes: Vec::new(),
+            rm_size: 0,
+            rm_size_str: String::from("sizing..."),
             last_modified,
+            age_source: AgeSource::Dir,
+            inspected: false,
         }
     }
 
+    /// Workspace members share the target directory of the workspace, any of their own are included as well
     pub fn cargo(path: PathBuf) -> Project {
-        let rm_paths = vec![path.join(PathBuf::from("target"))];
+        let mut rm_paths = vec![path.join(PathBuf::from("target"))];
+        let (members, excluded) = cargo_workspace_members(&path);
+        for member in find_workspace_members(&path, &members, &excluded, "Cargo.toml") {
+            rm_paths.push(member.join("target"));
+        }
         Project::new(path, ProjectType::Cargo, rm_paths)
     }
 
@@ -40,6 +74,34 @@ impl Project {
         Project::new(path, ProjectType::Composer, rm_paths)
     }
 
+    /// Build directories are recognized by their contents. For an in-source build only the generated
+    /// files are removed, see `cpp_build_dir` for build directories outside of any source tree
+    pub fn cpp(path: PathBuf) -> Project {
+        const IN_SOURCE_FILES: [&str; 2] = ["CMakeCache.txt", "CMakeFiles"];
+
+        let mut rm_paths = vec![];
+        if is_cpp_build_dir(&path) {
+            rm_paths.extend(IN_SOURCE_FILES.iter().map(|file| path.join(file)));
+        }
+        for dir in find_entries(&path, |_| true).into_iter().filter(|dir| dir.is_dir()) {
+            let name = dir.file_name().unwrap().to_string_lossy();
+            if name.starts_with("cmake-build-") || is_cpp_build_dir(&dir) {
+                rm_paths.push(dir);
+            } else if name == "build" || name == "out" {
+                // e.g. build/debug and build/release
+                rm_paths.extend(find_entries(&dir, |_| true).into_iter().filter(|dir| is_cpp_build_dir(dir)));
+            }
+        }
+        rm_paths.sort();
+        Project::new(path, ProjectType::Cpp, rm_paths)
+    }
+
+    /// A build directory outside of any source tree, which is a project of its own
+    pub fn cpp_build_dir(path: PathBuf) -> Project {
+        let rm_paths = vec![path.to_owned()];
+        Project::new(path, ProjectType::Cpp, rm_paths)
+    }
+
     pub fn dotnet(path: PathBuf) -> Project {
         let rm_paths = vec![
             path.join(PathBuf::from("bin")),
@@ -48,11 +110,22 @@ impl Project {
         Project::new(path, ProjectType::Dotnet, rm_paths)
     }
 
+    pub fn flutter(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join(PathBuf::from("build")),
+            path.join(PathBuf::from(".dart_tool")),
+            path.join(PathBuf::from("ios").join("Pods")),
+            path.join(PathBuf::from("macos").join("Pods")),
+            path.join(PathBuf::from("android").join(".gradle")),
+        ];
+        Project::new(path, ProjectType::Flutter, rm_paths)
+    }
+
     pub fn golang(path: PathBuf) -> Project {
         let dir_name = PathBuf::from(path.file_name().unwrap());
         let rm_paths = if !cfg!(windows) {
             vec![
-                path.join(dir_name.to_owned()),
+                path.join(&dir_name),
                 path.join(dir_name.with_extension("test")),
             ]
         } else {
@@ -65,10 +138,33 @@ impl Project {
     }
 
     pub fn gradle(path: PathBuf) -> Project {
-        let rm_paths = vec![path.join(PathBuf::from("build"))];
+        const GRADLE_DIRS: [&str; 4] = ["build", ".gradle", ".cxx", ".externalNativeBuild"];
+
+        let mut module_dirs = vec![path.to_owned()];
+        match gradle_settings_includes(&path) {
+            Some(includes) => module_dirs.extend(includes.iter().map(|module| path.join(module))),
+            None => find_gradle_modules(&path, &mut module_dirs),
+        }
+        let rm_paths = module_dirs.iter()
+            .flat_map(|dir| GRADLE_DIRS.iter().map(|name| dir.join(name)))
+            .collect();
         Project::new(path, ProjectType::Gradle, rm_paths)
     }
 
+    pub fn haskell(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join(".stack-work"),
+            path.join("dist-newstyle"),
+        ];
+        Project::new(path, ProjectType::Haskell, rm_paths)
+    }
+
+    pub fn maven(path: PathBuf) -> Project {
+        let mut rm_paths = vec![];
+        collect_maven_targets(&path, &mut rm_paths);
+        Project::new(path, ProjectType::Maven, rm_paths)
+    }
+
     pub fn misc(path: PathBuf, rm_paths: Vec<PathBuf>) -> Project {
         Project::new(path, ProjectType::Misc, rm_paths)
     }
@@ -81,33 +177,250 @@ impl Project {
         Project::new(path, ProjectType::Mix, rm_paths)
     }
 
+    /// Includes the node_modules of every npm, Yarn or pnpm workspace package
     pub fn node(path: PathBuf) -> Project {
-        let rm_paths = vec![path.join(PathBuf::from("node_modules"))];
+        let mut rm_paths = vec![path.join(PathBuf::from("node_modules"))];
+        let patterns = node_workspace_patterns(&path);
+        let (excluded, members): (Vec<String>, Vec<String>) = patterns.into_iter().partition(|p| p.starts_with('!'));
+        let excluded: Vec<String> = excluded.iter().map(|p| p[1..].to_owned()).collect();
+        for member in find_workspace_members(&path, &members, &excluded, "package.json") {
+            rm_paths.push(member.join("node_modules"));
+        }
         Project::new(path, ProjectType::Node, rm_paths)
     }
 
-    pub fn delete(&mut self) -> Option<String> {
-        let mut message = String::from("");
-        for path in &self.rm_paths {
-            let res = if path.is_dir() {
-                remove_dir_all(path)
-            } else {
-                remove_file(path)
+    pub fn ocaml(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join("_build"),
+            path.join("_opam"),
+        ];
+        Project::new(path, ProjectType::Ocaml, rm_paths)
+    }
+
+    pub fn python(path: PathBuf) -> Project {
+        const PYTHON_DIRS: [&str; 8] = [
+            ".venv", ".pytest_cache", ".mypy_cache", ".ruff_cache", ".tox", ".nox", "build", "dist",
+        ];
+
+        let mut rm_paths: Vec<PathBuf> = PYTHON_DIRS.iter().map(|dir| path.join(dir)).collect();
+        if is_virtualenv(&path.join("venv")) { rm_paths.push(path.join("venv")); }
+        for dir in [path.to_owned(), path.join("src")] {
+            rm_paths.extend(find_entries(&dir, |name| name.ends_with(".egg-info")));
+        }
+        let pycaches = find_dirs_recursive(&path, "__pycache__", &rm_paths);
+        rm_paths.extend(pycaches);
+        Project::new(path, ProjectType::Python, rm_paths)
+    }
+
+    pub fn ruby(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join(PathBuf::from("vendor").join("bundle")),
+            path.join(PathBuf::from(".bundle")),
+        ];
+        Project::new(path, ProjectType::Ruby, rm_paths)
+    }
+
+    pub fn scala(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join("target"),
+            path.join("project").join("target"),
+            path.join(".bloop"),
+            path.join(".metals"),
+            path.join("out"),
+        ];
+        Project::new(path, ProjectType::Scala, rm_paths)
+    }
+
+    pub fn swift(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join(".build"),
+            path.join("Pods"),
+            path.join("DerivedData"),
+        ];
+        Project::new(path, ProjectType::Swift, rm_paths)
+    }
+
+    pub fn zig(path: PathBuf) -> Project {
+        let rm_paths = vec![
+            path.join(PathBuf::from("zig-cache")),
+            path.join(PathBuf::from(".zig-cache")),
+            path.join(PathBuf::from("zig-out")),
+        ];
+        Project::new(path, ProjectType::Zig, rm_paths)
+    }
+
+    pub fn custom(path: PathBuf, name: &'static str, artifacts: &[String]) -> Project {
+        let rm_paths = artifacts.iter().map(|artifact| path.join(artifact)).collect();
+        Project::new(path, ProjectType::Custom(name), rm_paths)
+    }
+
+    /// Adds the types and artifacts of other to this project
+    pub fn merge(&mut self, other: Project) {
+        for (path, project_type) in other.rm_paths.into_iter().zip(other.rm_types) {
+            if self.rm_paths.contains(&path) { continue; }
+            self.rm_paths.push(path);
+            self.rm_types.push(project_type);
+        }
+        for project_type in other.project_types {
+            if !self.project_types.contains(&project_type) { self.project_types.push(project_type); }
+        }
+    }
+
+    /// Keeps only the rm_paths (and their types) for which keep returns true
+    pub fn retain_rm_paths<F: Fn(&Path) -> bool>(&mut self, keep: F) {
+        let (rm_paths, rm_types) = self.rm_paths.drain(..)
+            .zip(self.rm_types.drain(..))
+            .filter(|(path, _)| keep(path))
+            .unzip();
+        self.rm_paths = rm_paths;
+        self.rm_types = rm_types;
+    }
+
+    pub fn project_type(&self) -> ProjectType {
+        self.project_types[0]
+    }
+
+    pub fn is_enabled(&self, project_type: ProjectType) -> bool {
+        !self.disabled_types.contains(&project_type)
+    }
+
+    /// Enables or disables the artifacts of one of the project's types, at least one type is always left enabled.
+    /// Returns false if the type could not be toggled
+    pub fn toggle_type(&mut self, project_type: ProjectType) -> bool {
+        if let Some(idx) = self.disabled_types.iter().position(|t| *t == project_type) {
+            self.disabled_types.remove(idx);
+        } else if self.disabled_types.len() + 1 < self.project_types.len() && self.project_types.contains(&project_type) {
+            self.disabled_types.push(project_type);
+        } else {
+            return false;
+        }
+        if self.is_sized() {
+            let rm_sizes = std::mem::take(&mut self.rm_sizes);
+            self.set_rm_sizes(rm_sizes);
+        }
+        true
+    }
+
+    /// Reads what can't be told from the project directory alone: the git status and the age of the project
+    pub fn inspect(&mut self, age_source: AgeSource) {
+        self.check_vcs();
+        self.compute_age(age_source);
+        self.inspected = true;
+    }
+
+    pub fn is_inspected(&self) -> bool {
+        self.inspected
+    }
+
+    /// Reads the status of the git repository containing the project, and checks the existing
+    /// artifacts against its ignore rules and index
+    fn check_vcs(&mut self) {
+        let Some(repo) = git::Repo::find(&self.path) else { return };
+
+        self.git = Some(repo.status().clone());
+        self.vcs_warnings = self.rm_paths.iter()
+            .filter(|path| path.symlink_metadata().is_ok())
+            .filter_map(|path| repo.check(path).map(|warning| (path.to_owned(), warning)))
+            .collect();
+    }
+
+    pub fn vcs_warning(&self, path: &Path) -> Option<VcsWarning> {
+        self.vcs_warnings.iter().find(|(p, _)| p == path).map(|(_, warning)| *warning)
+    }
+
+    pub fn has_tracked(&self) -> bool {
+        self.vcs_warnings.iter().any(|(_, warning)| *warning == VcsWarning::Tracked)
+    }
+
+    /// Sets how long ago the project was last modified, should be called after `check_vcs`
+    fn compute_age(&mut self, source: AgeSource) {
+        self.age_source = source;
+        self.last_modified = match source {
+            AgeSource::Source => self.newest_file_time(|meta| meta.modified()),
+            AgeSource::Commit => self.git.as_ref().and_then(|git| git.last_commit)
+                .or_else(|| self.newest_file_time(|meta| meta.modified())),
+            AgeSource::Atime => self.newest_file_time(|meta| meta.accessed()),
+            AgeSource::Dir => None,
+        }
+        .or_else(|| get_time_since_last_mod(&self.path));
+    }
+
+    /// Days since the newest file in the project was modified or accessed, not counting artifacts
+    fn newest_file_time(&self, time: fn(&Metadata) -> io::Result<SystemTime>) -> Option<u64> {
+        newest_file_time(&self.path, &self.rm_paths, time).and_then(days_since)
+    }
+
+    pub fn compute_size(&mut self) {
+        self.set_rm_sizes(get_rm_sizes(&self.rm_paths));
+    }
+
+    pub fn set_rm_sizes(&mut self, rm_sizes: Vec<u64>) {
+        self.rm_sizes = rm_sizes;
+        self.rm_size = self.rm_sizes.iter()
+            .zip(&self.rm_types)
+            .filter(|(_, project_type)| self.is_enabled(**project_type))
+            .map(|(size, _)| size)
+            .sum();
+        self.rm_size_str = bytes_to_string(self.rm_size);
+    }
+
+    pub fn is_sized(&self) -> bool {
+        self.rm_sizes.len() == self.rm_paths.len()
+    }
+
+    pub fn sort_key(&self) -> (ProjectType, Reverse<u64>) {
+        (self.project_type(), Reverse(self.rm_size))
+    }
+
+    /// Deletes the artifacts of all enabled types, if skip_unsafe is set artifacts with a vcs warning are left alone
+    pub fn delete(&mut self, mode: DeleteMode, skip_unsafe: bool) -> Deletion {
+        let mut deletion = Deletion { mode, ..Deletion::default() };
+        let mut batch = None;
+        for (path, project_type) in self.rm_paths.iter().zip(&self.rm_types) {
+            if !self.is_enabled(*project_type) || path.symlink_metadata().is_err() { continue; }
+            if let Some(warning) = self.vcs_warning(path).filter(|_| skip_unsafe) {
+                deletion.skipped.push((path.to_owned(), warning));
+                continue;
+            }
+
+            let res = match mode {
+                DeleteMode::Remove if path.is_dir() => remove_dir_all(path),
+                DeleteMode::Remove => remove_file(path),
+                DeleteMode::Trash => trash::move_to_trash(path),
+                DeleteMode::Quarantine => match &mut batch {
+                    Some(batch) => Ok(batch),
+                    None => quarantine::Batch::new().map(|b| batch.insert(b)),
+                }.and_then(|batch| batch.add(path)),
             };
 
             match res {
-                Ok(_) => message += format!("Removed {:?}\n", path).as_str(),
-                Err(e) => message += format!("Unable to remove {:?}: {}\n", path, e).as_str(),
+                Ok(_) => deletion.removed.push(path.to_owned()),
+                Err(e) => deletion.errors.push((path.to_owned(), e)),
             }
         }
-        self.rm_size_str = bytes_to_string(self.rm_size);
-        self.last_modified = get_time_since_last_mod(&self.path);
+        if let Some(batch) = batch {
+            if !deletion.removed.is_empty() { deletion.quarantine_id = Some(batch.id.to_owned()); }
+            batch.discard_if_empty();
+        }
+        let rm_size = self.rm_size;
+        self.refresh();
+        deletion.freed = rm_size.saturating_sub(self.rm_size);
 
-        message.pop();
-        if message.is_empty() {
-            None
-        } else {
-            Some(message)
+        deletion
+    }
+
+    /// Moves artifacts quarantined by a previous deletion back into the project
+    pub fn restore(&mut self, quarantine_id: &str) -> io::Result<Vec<PathBuf>> {
+        let res = quarantine::restore(quarantine_id);
+        self.refresh();
+        res
+    }
+
+    fn refresh(&mut self) {
+        self.compute_size();
+        // removing artifacts only changes the modification time of the project directory itself
+        if self.age_source == AgeSource::Dir {
+            self.last_modified = get_time_since_last_mod(&self.path);
         }
     }
 
@@ -122,11 +435,15 @@ impl Project {
         path.display().to_string()
     }
 
+    /// Types joined with '+', e.g. "Node+Cargo"
     pub fn type_string(&self) -> String {
-        if self.project_type == ProjectType::Misc {
+        if self.project_type() == ProjectType::Misc {
             return format!("Misc ({})", self.get_rm_path_str())
         }
-        format!("{:?}", self.project_type)
+        self.project_types.iter()
+            .map(|project_type| project_type.to_string())
+            .collect::<Vec<String>>()
+            .join("+")
     }
 
     fn get_rm_path_str(&self) -> &str {
@@ -134,21 +451,115 @@ impl Project {
     }
 }
 
-#[derive(Debug, Copy, Clone, Ord, Eq, PartialOrd, PartialEq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum DeleteMode {
+    #[default]
+    Remove,
+    /// Move artifacts to the system trash
+    Trash,
+    /// Move artifacts to the polykill quarantine directory, see `quarantine::purge`
+    Quarantine,
+}
+
+impl DeleteMode {
+    pub fn verb(&self) -> &'static str {
+        match self {
+            DeleteMode::Remove => "remove",
+            DeleteMode::Trash => "trash",
+            DeleteMode::Quarantine => "quarantine",
+        }
+    }
+
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            DeleteMode::Remove => "Removed",
+            DeleteMode::Trash => "Trashed",
+            DeleteMode::Quarantine => "Quarantined",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Deletion {
+    pub mode: DeleteMode,
+    pub removed: Vec<PathBuf>,
+    pub errors: Vec<(PathBuf, io::Error)>,
+    /// Artifacts left alone because of a vcs warning
+    pub skipped: Vec<(PathBuf, VcsWarning)>,
+    pub freed: u64,
+    /// Can be passed to `Project::restore` to undo the deletion
+    pub quarantine_id: Option<String>,
+}
+
+impl Deletion {
+    pub fn message(&self) -> Option<String> {
+        let mut lines: Vec<String> = self.removed.iter()
+            .map(|path| format!("{} {:?}", self.mode.past_tense(), path))
+            .collect();
+        for (path, e) in &self.errors {
+            lines.push(format!("Unable to {} {:?}: {}", self.mode.verb(), path, e));
+        }
+        lines.extend(self.skipped_lines());
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+
+    pub fn skipped_lines(&self) -> Vec<String> {
+        self.skipped.iter()
+            .map(|(path, warning)| format!("Skipped {:?}: {}", path, warning.describe()))
+            .collect()
+    }
+}
+
+/// What the age of a project (for sorting and `--older-than`) is based on
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum AgeSource {
+    /// Newest modification time of any file in the project, excluding artifacts, VCS directories and nested projects
+    #[default]
+    Source,
+    /// Time of the last commit in the project's git repository (falls back to source)
+    Commit,
+    /// Newest access time of any file in the project, excluding artifacts, VCS directories and nested projects
+    Atime,
+    /// Modification time of the project directory
+    Dir,
+}
+
+#[derive(Debug, Copy, Clone, Ord, Eq, PartialOrd, PartialEq, ValueEnum)]
 pub enum ProjectType {
     Cargo,
     Composer,
+    Cpp,
     Dotnet,
+    Flutter,
+    #[value(alias = "go")]
     Golang,
     Gradle,
+    Haskell,
+    Maven,
     Misc,
     Mix,
     Node,
+    Ocaml,
+    Python,
+    Ruby,
+    Scala,
+    Swift,
+    Zig,
+    #[value(skip)]
+    Custom(&'static str),
 }
 
 impl Display for ProjectType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            ProjectType::Custom(name) => write!(f, "{}", name),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
@@ -160,7 +571,6 @@ fn truncate_path(path: &PathBuf, n: usize) -> (PathBuf, bool) {
 }
 
 fn get_time_since_last_mod(path: &PathBuf) -> Option<u64> {
-    const SECONDS_PER_DAY: u64 = 86400;
     let meta = metadata(path);
 
     if meta.is_err() { return None; }
@@ -168,47 +578,284 @@ fn get_time_since_last_mod(path: &PathBuf) -> Option<u64> {
     let last_mod = meta.modified();
 
     if last_mod.is_err() { return None; }
-    let last_mod = last_mod.unwrap();
-    let time_since = SystemTime::now().duration_since(last_mod);
+    days_since(last_mod.unwrap())
+}
 
-    if time_since.is_err() { return None; }
-    Some(time_since.unwrap().as_secs() / Duration::from_secs(SECONDS_PER_DAY).as_secs())
+/// Newest time of any file below path, skipping the given paths, VCS and artifact directories, nested projects
+/// and symlinks. Subdirectories containing any of the skipped paths (e.g. workspace members) aren't nested projects
+fn newest_file_time(path: &Path, skip: &[PathBuf], time: fn(&Metadata) -> io::Result<SystemTime>) -> Option<SystemTime> {
+    const VCS_DIRS: [&str; 3] = [".git", ".svn", ".hg"];
+    // artifacts of any project type, which aren't necessarily artifacts of the project itself
+    const ARTIFACT_DIRS: [&str; 22] = [
+        "node_modules", "target", "__pycache__", ".venv", ".pytest_cache", ".mypy_cache", ".ruff_cache", ".tox",
+        ".nox", ".gradle", ".dart_tool", ".stack-work", "dist-newstyle", "_build", "_opam", ".bloop", ".metals",
+        ".build", "Pods", "DerivedData", "zig-cache", "zig-out",
+    ];
+
+    let Ok(entries) = read_dir(path) else { return None };
+    let entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+    entries.into_par_iter()
+        .filter(|entry| !skip.contains(&entry.path()))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            if meta.is_dir() {
+                let (name, path) = (entry.file_name(), entry.path());
+                if VCS_DIRS.iter().chain(&ARTIFACT_DIRS).any(|dir| name == *dir) { return None; }
+                if is_virtualenv(&path) || is_cpp_build_dir(&path) { return None; }
+                if is_project_dir(&path) && !skip.iter().any(|skipped| skipped.starts_with(&path)) { return None; }
+                newest_file_time(&path, skip, time)
+            } else if meta.is_file() {
+                time(&meta).ok()
+            } else {
+                None
+            }
+        })
+        .max()
 }
 
-fn get_rm_size(rm_paths: &Vec<PathBuf>) -> u64 {
-    let mut size = 0;
-    for path in rm_paths {
-        let path_exists = path.try_exists();
-        if path_exists.is_err() { continue; }
+/// Adds the target directory of the maven project at path, and those of any modules listed in its pom.xml
+fn collect_maven_targets(path: &Path, rm_paths: &mut Vec<PathBuf>) {
+    let target = path.join("target");
+    if rm_paths.contains(&target) { return; }
+    rm_paths.push(target);
 
-        let entry_size = compute_size(path);
-        if entry_size.is_err() { continue; }
+    let Ok(pom) = read_to_string(path.join("pom.xml")) else { return };
+    for module in parse_maven_modules(&pom) {
+        // modules outside the project directory are found as projects of their own
+        if Path::new(module).components().any(|c| c == std::path::Component::ParentDir) { continue; }
 
-        size += entry_size.unwrap();
+        let module_path = path.join(module);
+        let module_path = if module.ends_with(".xml") {
+            module_path.parent().unwrap().to_owned()
+        } else {
+            module_path
+        };
+        if module_path.join("pom.xml").is_file() {
+            collect_maven_targets(&module_path, rm_paths);
+        }
     }
-    size
 }
 
-fn compute_size(path: &PathBuf) -> io::Result<u64> {
+/// Contents of every <module> element in a pom.xml, including those in profiles
+fn parse_maven_modules(pom: &str) -> Vec<&str> {
+    let mut modules = vec![];
+    let mut rest = pom;
+    while let Some(start) = rest.find("<module>") {
+        rest = &rest[start + "<module>".len()..];
+        let Some(end) = rest.find("</module>") else { break };
+
+        let preceding = &pom[..pom.len() - rest.len()];
+        let in_comment = preceding.rfind("<!--").is_some_and(|open| preceding.rfind("-->").map_or(true, |close| close < open));
+        if !in_comment { modules.push(rest[..end].trim()); }
+        rest = &rest[end..];
+    }
+    modules
+}
+
+/// Module directories (relative to path) included by settings.gradle(.kts), None if there is no settings file
+fn gradle_settings_includes(path: &Path) -> Option<Vec<PathBuf>> {
+    let settings = read_to_string(path.join("settings.gradle"))
+        .or_else(|_| read_to_string(path.join("settings.gradle.kts")))
+        .ok()?;
+
+    let mut includes = vec![];
+    let mut in_include = false;
+    for line in settings.lines().map(str::trim) {
+        if line.starts_with("//") { continue; }
+
+        let starts_include = line.strip_prefix("include")
+            .is_some_and(|rest| rest.starts_with([' ', '(', '\'', '"']));
+        if !starts_include && !in_include { continue; }
+
+        // e.g. include ':app', ':libs:core' or include(":app")
+        let quoted = line.split(['\'', '"']).skip(1).step_by(2);
+        for module in quoted.map(|name| name.trim_start_matches(':')).filter(|name| !name.is_empty()) {
+            includes.push(module.split(':').collect());
+        }
+        in_include = line.ends_with(',') || line.ends_with('(');
+    }
+    Some(includes)
+}
+
+/// Adds every directory below dir containing a build.gradle(.kts), skipping hidden, build and node_modules directories
+fn find_gradle_modules(dir: &Path, modules: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(dir) else { return };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) { continue; }
+
+        let file_name = entry.file_name();
+        if file_name.as_encoded_bytes().starts_with(b".") || file_name == "build" || file_name == "node_modules" {
+            continue;
+        }
+
+        let path = entry.path();
+        if path.join("build.gradle").is_file() || path.join("build.gradle.kts").is_file() {
+            modules.push(path.to_owned());
+        }
+        find_gradle_modules(&path, modules);
+    }
+}
+
+/// Member and excluded patterns of the [workspace] table in Cargo.toml
+fn cargo_workspace_members(path: &Path) -> (Vec<String>, Vec<String>) {
+    let Ok(manifest) = read_to_string(path.join("Cargo.toml")) else { return Default::default() };
+    let Ok(manifest) = manifest.parse::<toml::Table>() else { return Default::default() };
+    let Some(workspace) = manifest.get("workspace") else { return Default::default() };
+
+    let strings = |key: &str| workspace.get(key)
+        .and_then(|value| value.as_array())
+        .map(|values| values.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    (strings("members"), strings("exclude"))
+}
+
+/// Workspace patterns from pnpm-workspace.yaml, or the workspaces field of package.json (npm and Yarn).
+/// Patterns starting with '!' exclude packages
+fn node_workspace_patterns(path: &Path) -> Vec<String> {
+    if let Ok(pnpm) = read_to_string(path.join("pnpm-workspace.yaml")) {
+        return parse_pnpm_packages(&pnpm);
+    }
+
+    let Ok(package) = read_to_string(path.join("package.json")) else { return Vec::new() };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&package) else { return Vec::new() };
+    let workspaces = match &package["workspaces"] {
+        serde_json::Value::Object(workspaces) => workspaces.get("packages").cloned().unwrap_or_default(),
+        workspaces => workspaces.clone(),
+    };
+    let Some(workspaces) = workspaces.as_array() else { return Vec::new() };
+    workspaces.iter().filter_map(|w| w.as_str().map(String::from)).collect()
+}
+
+/// Items of the top level packages list in a pnpm-workspace.yaml
+fn parse_pnpm_packages(yaml: &str) -> Vec<String> {
+    let mut packages = vec![];
+    let mut in_packages = false;
+    for line in yaml.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') { continue; }
+        if !line.starts_with(char::is_whitespace) && !trimmed.starts_with('-') {
+            in_packages = trimmed.starts_with("packages:");
+            continue;
+        }
+        let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) else { continue };
+        let item = item.split(" #").next().unwrap().trim();
+        packages.push(item.trim_matches(|c| c == '\'' || c == '"').to_owned());
+    }
+    packages
+}
+
+/// Directories inside path that contain a manifest and match one of the member globs but none of
+/// the excluded ones. Members outside of path are found as projects of their own
+fn find_workspace_members(path: &Path, members: &[String], excluded: &[String], manifest: &str) -> Vec<PathBuf> {
+    let normalize = |pattern: &String| pattern.trim_start_matches("./").trim_end_matches('/').to_owned();
+    let members: Vec<String> = members.iter().map(normalize).filter(|m| !m.is_empty() && !m.starts_with("..")).collect();
+    let excluded: Vec<String> = excluded.iter().map(normalize).collect();
+    if members.is_empty() { return Vec::new(); }
+
+    let mut dirs = vec![];
+    collect_workspace_dirs(path, path, &members, &mut dirs);
+    dirs.retain(|dir| {
+        let relative = dir.strip_prefix(path).unwrap().to_string_lossy();
+        !excluded.iter().any(|pattern| git::wildmatch(pattern.as_bytes(), relative.as_bytes()))
+        && dir.join(manifest).is_file()
+    });
+    dirs
+}
+
+/// Adds directories below dir matching one of the member globs, skipping hidden directories and node_modules
+fn collect_workspace_dirs(root: &Path, dir: &Path, members: &[String], dirs: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(dir) else { return };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) { continue; }
+        let file_name = entry.file_name();
+        if file_name.as_encoded_bytes()[0] == b'.' || file_name == "node_modules" { continue; }
+
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap().to_string_lossy().into_owned();
+        if members.iter().any(|member| git::wildmatch(member.as_bytes(), relative.as_bytes())) {
+            dirs.push(path.to_owned());
+        }
+        // only descend as far as a member could match
+        let depth = relative.matches('/').count();
+        let deeper = members.iter().any(|member| member.contains("**") || member.matches('/').count() > depth);
+        if deeper { collect_workspace_dirs(root, &path, members, dirs); }
+    }
+}
+
+/// Whether path is a CMake, Ninja or Meson build directory. A build.ninja can be written by hand,
+/// so it only counts once ninja has left its own state files next to it
+pub fn is_cpp_build_dir(path: &Path) -> bool {
+    const GENERATED_FILES: [&str; 2] = ["CMakeCache.txt", "meson-private"];
+    const NINJA_STATE_FILES: [&str; 2] = [".ninja_log", ".ninja_deps"];
+
+    GENERATED_FILES.iter().any(|file| path.join(file).exists())
+    || path.join("build.ninja").exists() && NINJA_STATE_FILES.iter().any(|file| path.join(file).exists())
+}
+
+pub fn is_virtualenv(path: &Path) -> bool {
+    path.join("pyvenv.cfg").is_file()
+}
+
+/// Entries directly inside dir with names matching the predicate
+fn find_entries<F: Fn(&str) -> bool>(dir: &Path, matches: F) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(dir) else { return Vec::new() };
+
+    entries.filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().is_some_and(&matches))
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Directories named name anywhere below dir, skipping hidden directories, virtualenvs, node_modules
+/// and the given paths (e.g. artifacts that already include them)
+fn find_dirs_recursive(dir: &Path, name: &str, skip: &[PathBuf]) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(dir) else { return Vec::new() };
+
+    let mut dirs = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) || skip.contains(&path) { continue; }
+
+        let file_name = entry.file_name();
+        if file_name == name {
+            dirs.push(path);
+        } else if file_name.as_encoded_bytes()[0] != b'.' && file_name != "node_modules" && !is_virtualenv(&path) {
+            dirs.append(&mut find_dirs_recursive(&path, name, skip));
+        }
+    }
+    dirs
+}
+
+pub fn get_rm_sizes(rm_paths: &[PathBuf]) -> Vec<u64> {
+    rm_paths.par_iter()
+        .map(|path| compute_size(path).unwrap_or(0))
+        .collect()
+}
+
+pub fn compute_size(path: &PathBuf) -> io::Result<u64> {
     if !path.is_dir() {
         return Ok(path.metadata()?.len());
     }
 
-    fn dir_size(mut dir: ReadDir) -> io::Result<u64> {
-        dir.try_fold(0, |acc, file| {
-            let file = file?;
-            let size = match file.metadata()? {
-                data if data.is_dir() => dir_size(read_dir(file.path())?)?,
-                data => data.len(),
-            };
-            Ok(acc + size)
-        })
+    fn dir_size(dir: ReadDir) -> io::Result<u64> {
+        let files: Vec<_> = dir.collect();
+        files.into_par_iter()
+            .map(|file| {
+                let file = file?;
+                match file.metadata()? {
+                    data if data.is_dir() => dir_size(read_dir(file.path())?),
+                    data => Ok(data.len()),
+                }
+            })
+            .sum()
     }
 
     dir_size(read_dir(path)?)
 }
 
-fn bytes_to_string(bytes: u64) -> String {
+pub fn bytes_to_string(bytes: u64) -> String {
     const KB: u64 = 1000;
     const BASE: f64 = 6.931471806;
     const PREFIXES: &[u8] = "KMGT".as_bytes();
@@ -218,7 +865,7 @@ fn bytes_to_string(bytes: u64) -> String {
     } else {
         let size = bytes as f64;
         let exponent = match (size.ln() / BASE) as usize {
-            e if e == 0 => 1,
+            0 => 1,
             e => e,
         };
 
@@ -0,0 +1,173 @@
+use std::env;
+use std::fs::{self, create_dir, create_dir_all, read_dir, read_to_string, remove_dir, remove_dir_all, rename};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::data_dir;
+use crate::project::compute_size;
+use crate::util::{is_cross_device, now, SECONDS_PER_DAY};
+
+const MANIFEST_FILE: &str = "manifest.toml";
+/// Used next to the project when its artifacts are on a different filesystem than the quarantine directory
+const LOCAL_QUARANTINE_DIR: &str = ".polykill-quarantine";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Seconds since the unix epoch
+    deleted: u64,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    original: PathBuf,
+    quarantined: PathBuf,
+}
+
+/// Artifacts quarantined by a single deletion, which can be restored or purged together
+pub struct Batch {
+    pub id: String,
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Batch {
+    pub fn new() -> io::Result<Batch> {
+        let quarantine_dir = quarantine_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unable to locate home directory"))?;
+        create_dir_all(&quarantine_dir)?;
+
+        let deleted = now();
+        for n in 0.. {
+            let id = format!("{}-{}", deleted, n);
+            let dir = quarantine_dir.join(&id);
+            match create_dir(&dir) {
+                Ok(_) => return Ok(Batch { id, dir, manifest: Manifest { deleted, entries: vec![] } }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!()
+    }
+
+    /// Moves path into the quarantine, this is a rename so path must be on the same filesystem as
+    /// either the quarantine directory or its parent directory
+    pub fn add(&mut self, path: &Path) -> io::Result<()> {
+        let path = &env::current_dir()?.join(path);
+        let name = format!("{}-{}", self.manifest.entries.len(), path.file_name().unwrap().to_string_lossy());
+        let mut quarantined = self.dir.join(&name);
+
+        if let Err(e) = rename(path, &quarantined) {
+            if !is_cross_device(&e) { return Err(e); }
+
+            let local_dir = path.parent().unwrap().join(LOCAL_QUARANTINE_DIR).join(&self.id);
+            create_dir_all(&local_dir)?;
+            quarantined = local_dir.join(&name);
+            rename(path, &quarantined)?;
+        }
+
+        self.manifest.entries.push(Entry { original: path.to_owned(), quarantined });
+        self.write_manifest()
+    }
+
+    /// Removes the batch directory if nothing was added to it
+    pub fn discard_if_empty(self) {
+        if self.manifest.entries.is_empty() { let _ = remove_dir(&self.dir); }
+    }
+
+    fn write_manifest(&self) -> io::Result<()> {
+        let contents = toml::to_string(&self.manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(self.dir.join(MANIFEST_FILE), contents)
+    }
+
+    fn load(dir: PathBuf) -> io::Result<Batch> {
+        let contents = read_to_string(dir.join(MANIFEST_FILE))?;
+        let manifest = toml::from_str(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let id = dir.file_name().unwrap().to_string_lossy().into_owned();
+        Ok(Batch { id, dir, manifest })
+    }
+
+    /// Removes the batch directory, as well as any local quarantine directories it used
+    fn remove(self) -> io::Result<()> {
+        for entry in &self.manifest.entries {
+            let entry_dir = entry.quarantined.parent().unwrap();
+            if entry_dir == self.dir { continue; }
+
+            let _ = remove_dir_all(entry_dir);
+            let _ = remove_dir(entry_dir.parent().unwrap());
+        }
+        remove_dir_all(&self.dir)
+    }
+}
+
+/// Moves the artifacts of a batch back to where they came from, returns the restored paths
+pub fn restore(id: &str) -> io::Result<Vec<PathBuf>> {
+    let quarantine_dir = quarantine_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unable to locate home directory"))?;
+    let mut batch = Batch::load(quarantine_dir.join(id))?;
+
+    let mut restored = vec![];
+    let mut res = Ok(());
+    for entry in std::mem::take(&mut batch.manifest.entries) {
+        if res.is_ok() {
+            res = restore_entry(&entry);
+            if res.is_ok() {
+                restored.push(entry.original);
+                continue;
+            }
+        }
+        batch.manifest.entries.push(entry);
+    }
+
+    if batch.manifest.entries.is_empty() {
+        batch.remove()?;
+    } else {
+        batch.write_manifest()?;
+    }
+    res.map(|_| restored)
+}
+
+fn restore_entry(entry: &Entry) -> io::Result<()> {
+    if entry.original.symlink_metadata().is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{:?} already exists", entry.original)));
+    }
+    rename(&entry.quarantined, &entry.original)
+}
+
+/// Permanently deletes artifacts quarantined at least days ago, returns the number of bytes freed and batches purged
+pub fn purge(days: u64) -> io::Result<(u64, usize)> {
+    let Some(quarantine_dir) = quarantine_dir() else { return Ok((0, 0)) };
+    if !quarantine_dir.is_dir() { return Ok((0, 0)); }
+
+    let now = now();
+    let mut freed = 0;
+    let mut purged = 0;
+    for entry in read_dir(quarantine_dir)? {
+        let batch_dir = entry?.path();
+        let batch = match Batch::load(batch_dir.to_owned()) {
+            Ok(batch) => batch,
+            Err(_) => {
+                // nothing was ever added to this batch
+                let _ = remove_dir(batch_dir);
+                continue;
+            }
+        };
+        if now.saturating_sub(batch.manifest.deleted) < days.saturating_mul(SECONDS_PER_DAY) { continue; }
+
+        for entry in &batch.manifest.entries {
+            freed += compute_size(&entry.quarantined).unwrap_or(0);
+        }
+        batch.remove()?;
+        purged += 1;
+    }
+    Ok((freed, purged))
+}
+
+fn quarantine_dir() -> Option<PathBuf> {
+    Some(data_dir()?.join("polykill").join("quarantine"))
+}
+
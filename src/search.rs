@@ -1,72 +1,231 @@
 use std::path::{Path, PathBuf};
 
-use crate::project::Project;
+use rayon::prelude::*;
 
-pub fn find_projects(path: &Path, max_depth: u32) -> Vec<Project> {
-    if max_depth == 0 { return Vec::new(); }
+use crate::project::{is_cpp_build_dir, is_virtualenv, AgeSource, Project, ProjectType};
 
-    let mut projects = Vec::new();
-    let entries = path.read_dir();
-    if entries.is_err() { return projects; }
+type Detector = fn(&Path) -> bool;
 
-    for entry in entries.unwrap() {
-        if entry.is_err() { continue; }
+/// How deep repositories are searched for (nested) projects
+const MAX_REPO_DEPTH: u32 = 8;
+/// How many directory levels of a repository, starting at its root, are checked for Misc projects
+const MISC_REPO_LEVELS: u32 = 3;
+
+/// Project detectors in order of precedence
+const DETECTORS: [(ProjectType, Detector); 18] = [
+    (ProjectType::Node, is_node),
+    (ProjectType::Cargo, is_cargo),
+    (ProjectType::Dotnet, is_dotnet),
+    (ProjectType::Golang, is_golang),
+    (ProjectType::Gradle, is_gradle),
+    (ProjectType::Maven, is_maven),
+    (ProjectType::Scala, is_scala),
+    (ProjectType::Composer, is_composer),
+    (ProjectType::Mix, is_mix),
+    (ProjectType::Haskell, is_haskell),
+    (ProjectType::Ocaml, is_ocaml),
+    (ProjectType::Python, is_python),
+    (ProjectType::Flutter, is_flutter),
+    (ProjectType::Swift, is_swift),
+    (ProjectType::Ruby, is_ruby),
+    (ProjectType::Zig, is_zig),
+    (ProjectType::Cpp, is_cpp),
+    (ProjectType::Misc, |path| is_misc_project(path).is_some()),
+];
+
+#[derive(Default)]
+pub struct SearchOptions {
+    /// Only include these project types (all types if empty)
+    pub types: Vec<ProjectType>,
+    /// Never include these project types
+    pub exclude_types: Vec<ProjectType>,
+    /// Canonical paths that are never searched or removed
+    pub exclude_paths: Vec<PathBuf>,
+    /// User defined project types, checked before the built-in ones
+    pub custom_types: Vec<CustomType>,
+    /// What the age of found projects is based on
+    pub age_source: AgeSource,
+    /// Called with each project (uninspected and unsized) as soon as it is found, instead of returning it
+    pub on_found: Option<Box<dyn Fn(Project) + Send + Sync>>,
+}
 
-        let path = entry.unwrap().path();
-        if !path.is_dir() || path.file_name().unwrap().as_encoded_bytes()[0] == b'.' { continue; }
+impl SearchOptions {
+    fn allows(&self, project_type: ProjectType) -> bool {
+        (self.types.is_empty() || self.types.contains(&project_type))
+        && !self.exclude_types.contains(&project_type)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        if self.exclude_paths.is_empty() { return false; }
 
-        if let Some(project) = check_for_project(path.clone()) {
-            projects.push(project);
-        } else {
-            projects.append(&mut find_projects(&path, max_depth - 1));
+        match path.canonicalize() {
+            Ok(path) => self.exclude_paths.iter().any(|excluded| path.starts_with(excluded)),
+            Err(_) => false,
         }
     }
-    projects
 }
 
-pub fn find_git_projects(path: &Path) -> Vec<Project> {
-    let mut projects = Vec::new();
-    let entries = path.read_dir();
-    if entries.is_err() { return projects; }
+#[derive(Debug)]
+pub struct CustomType {
+    pub name: &'static str,
+    /// File names identifying the project, a leading '*' matches by suffix (e.g. "*.csproj")
+    pub markers: Vec<String>,
+    pub artifacts: Vec<String>,
+}
 
-    for entry in entries.unwrap() {
-        if entry.is_err() { continue; }
+impl CustomType {
+    fn matches(&self, path: &Path) -> bool {
+        self.markers.iter().any(|marker| match marker.strip_prefix('*') {
+            Some(suffix) => contains_file_regex(path, suffix),
+            None => contains_entry(path, marker),
+        })
+    }
+}
 
-        let path = entry.unwrap().path();
-        if !path.is_dir() || path.file_name().unwrap().as_encoded_bytes()[0] == b'.' { continue; }
+pub fn find_projects(path: &Path, max_depth: u32, options: &SearchOptions) -> Vec<Project> {
+    find_nested_projects(path, max_depth, u32::MAX, false, &[], options)
+}
 
-        if is_repo(&path) {
-            if let Some(project) = check_for_project(path.clone()) {
-                projects.push(project);
+pub fn find_git_projects(path: &Path, options: &SearchOptions) -> Vec<Project> {
+    list_search_dirs(path, options).into_par_iter()
+        .flat_map(|path| {
+            if is_repo(&path) {
+                search_dir(&path, MAX_REPO_DEPTH, MISC_REPO_LEVELS, false, &[], options)
             } else {
-                projects.append(&mut find_projects(&path, 2));
+                find_git_projects(&path, options)
             }
-        } else {
-            projects.append(&mut find_git_projects(&path));
-        }
-    }
+        })
+        .collect()
+}
+
+/// Checks path for a project, then keeps searching its subdirectories for nested projects (e.g. in a
+/// monorepo). Artifacts of projects containing path are claimed, and are neither searched nor added again.
+/// Misc projects are only detected in the first misc_levels levels, starting at path
+fn search_dir(path: &Path, max_depth: u32, misc_levels: u32, nested: bool, claimed: &[PathBuf], options: &SearchOptions) -> Vec<Project> {
+    let mut projects = Vec::new();
+    let misc = !nested && misc_levels > 0;
+    let Some(artifacts) = check_for_project(path, nested, misc, claimed, options, &mut projects) else {
+        return find_nested_projects(path, max_depth, misc_levels.saturating_sub(1), nested, claimed, options);
+    };
+
+    let claimed = [claimed, &artifacts].concat();
+    projects.extend(find_nested_projects(path, max_depth, 0, true, &claimed, options));
     projects
 }
 
-fn check_for_project(path: PathBuf) -> Option<Project> {
-    if is_node(&path) {
-        Some(Project::node(path))
-    } else if is_cargo(&path) {
-        Some(Project::cargo(path))
-    } else if is_dotnet(&path) {
-        Some(Project::dotnet(path))
-    } else if is_golang(&path) {
-        Some(Project::golang(path))
-    } else if is_gradle(&path) {
-        Some(Project::gradle(path))
-    } else if is_composer(&path) {
-        Some(Project::composer(path))
-    } else if is_mix(&path) {
-        Some(Project::mix(path))
-    } else if let Some(rm_dir) = is_misc_project(&path) {
-        Some(Project::misc(path.to_owned(), vec![path.join(rm_dir)]))
+fn find_nested_projects(path: &Path, max_depth: u32, misc_levels: u32, nested: bool, claimed: &[PathBuf], options: &SearchOptions) -> Vec<Project> {
+    if max_depth == 0 { return Vec::new(); }
+
+    list_search_dirs(path, options).into_par_iter()
+        .filter(|path| !claimed.contains(path))
+        .flat_map(|path| search_dir(&path, max_depth - 1, misc_levels, nested, claimed, options))
+        .collect()
+}
+
+/// Subdirectories of path that should be searched, in directory order. Hidden directories are never
+/// descended into, but detectors still see them as markers or artifacts of their parent (e.g. .build)
+fn list_search_dirs(path: &Path, options: &SearchOptions) -> Vec<PathBuf> {
+    let entries = path.read_dir();
+    if entries.is_err() { return Vec::new(); }
+
+    entries.unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path.file_name().unwrap().as_encoded_bytes()[0] != b'.')
+        .filter(|path| !is_virtualenv(path))
+        .filter(|path| !options.is_excluded(path))
+        .collect()
+}
+
+/// Adds the project at path with all of its types that are included in the search. Returns the artifacts
+/// of every type detected at path, or None if path is not a project directory of any type
+///
+/// Misc projects are only detected if misc is set. Nested projects (inside another project) are only added
+/// with artifacts that exist and aren't claimed by a project containing them
+fn check_for_project(path: &Path, nested: bool, misc: bool, claimed: &[PathBuf], options: &SearchOptions, projects: &mut Vec<Project>) -> Option<Vec<PathBuf>> {
+    let mut detected: Vec<ProjectType> = options.custom_types.iter()
+        .filter(|custom| custom.matches(path))
+        .map(|custom| ProjectType::Custom(custom.name))
+        .collect();
+    detected.extend(DETECTORS.iter()
+        .filter(|(project_type, _)| misc || *project_type != ProjectType::Misc)
+        .filter(|(_, detector)| detector(path))
+        .map(|(project_type, _)| *project_type));
+    if detected.is_empty() { return None; }
+    // misc directories are only artifacts when there is nothing more specific, even if that type isn't searched for
+    if detected.len() > 1 { detected.retain(|project_type| *project_type != ProjectType::Misc); }
+
+    // a build directory is only removed as a whole when it holds no sources or other projects
+    let standalone_build_dir = detected == [ProjectType::Cpp] && is_cpp_build_dir(path)
+        && !has_cpp_sources(path) && !is_repo(path);
+
+    let mut artifacts = Vec::new();
+    let mut parts = Vec::new();
+    for project_type in &detected {
+        let part = match project_type {
+            ProjectType::Cpp if standalone_build_dir => Project::cpp_build_dir(path.to_owned()),
+            _ => create_project(path.to_owned(), *project_type, options),
+        };
+        // artifacts of types left out of the search are claimed too, they still aren't projects of their own
+        for rm_path in &part.rm_paths {
+            if !artifacts.contains(rm_path) { artifacts.push(rm_path.to_owned()); }
+        }
+        if options.allows(*project_type) { parts.push(part); }
+    }
+    let Some(first) = parts.first() else { return Some(artifacts) };
+
+    let mut project = Project::new(path.to_owned(), first.project_type(), vec![]);
+    for part in parts { project.merge(part); }
+
+    project.retain_rm_paths(|path| !options.is_excluded(path) && !claimed.iter().any(|c| c == path));
+    if nested { project.retain_rm_paths(|path| path.symlink_metadata().is_ok()); }
+    if project.rm_paths.is_empty() { return Some(artifacts); }
+
+    if let Some(on_found) = &options.on_found {
+        on_found(project);
     } else {
-        None
+        project.inspect(options.age_source);
+        project.compute_size();
+        projects.push(project);
+    }
+    Some(artifacts)
+}
+
+/// Whether path holds a project of a built-in type. C++ sources aren't counted, their subdirectories
+/// (with CMakeLists.txt files of their own) are usually part of the same build
+pub fn is_project_dir(path: &Path) -> bool {
+    DETECTORS.iter()
+        .filter(|(project_type, _)| !matches!(project_type, ProjectType::Cpp | ProjectType::Misc))
+        .any(|(_, detector)| detector(path))
+}
+
+fn create_project(path: PathBuf, project_type: ProjectType, options: &SearchOptions) -> Project {
+    match project_type {
+        ProjectType::Cargo => Project::cargo(path),
+        ProjectType::Composer => Project::composer(path),
+        ProjectType::Cpp => Project::cpp(path),
+        ProjectType::Dotnet => Project::dotnet(path),
+        ProjectType::Flutter => Project::flutter(path),
+        ProjectType::Golang => Project::golang(path),
+        ProjectType::Gradle => Project::gradle(path),
+        ProjectType::Haskell => Project::haskell(path),
+        ProjectType::Maven => Project::maven(path),
+        ProjectType::Misc => {
+            let rm_path = path.join(is_misc_project(&path).unwrap());
+            Project::misc(path, vec![rm_path])
+        }
+        ProjectType::Mix => Project::mix(path),
+        ProjectType::Node => Project::node(path),
+        ProjectType::Ocaml => Project::ocaml(path),
+        ProjectType::Python => Project::python(path),
+        ProjectType::Ruby => Project::ruby(path),
+        ProjectType::Scala => Project::scala(path),
+        ProjectType::Swift => Project::swift(path),
+        ProjectType::Zig => Project::zig(path),
+        ProjectType::Custom(name) => {
+            let custom = options.custom_types.iter().find(|custom| custom.name == name).unwrap();
+            Project::custom(path, name, &custom.artifacts)
+        }
     }
 }
 
@@ -88,9 +247,50 @@ fn is_golang(path: &Path) -> bool {
 fn is_gradle(path: &Path) -> bool {
     contains_entry(path, "build.gradle") || contains_entry(path, "build.gradle.kts")
 }
+fn is_maven(path: &Path) -> bool {
+    contains_entry(path, "pom.xml")
+}
+fn is_scala(path: &Path) -> bool {
+    contains_entry(path, "build.sbt") || contains_entry(path, "build.sc")
+}
+fn is_haskell(path: &Path) -> bool {
+    contains_entry(path, "stack.yaml") || contains_file_with_extension(path, "cabal")
+}
+fn is_ocaml(path: &Path) -> bool {
+    contains_entry(path, "dune-project")
+}
 fn is_composer(path: &Path) -> bool {
     contains_entry(path, "composer.json")
 }
+fn is_python(path: &Path) -> bool {
+    const PYTHON_FILES: [&str; 5] = ["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt", "Pipfile"];
+
+    PYTHON_FILES.iter().any(|file| contains_entry(path, file))
+    || is_virtualenv(&path.join(".venv"))
+    || is_virtualenv(&path.join("venv"))
+}
+fn is_flutter(path: &Path) -> bool {
+    contains_entry(path, "pubspec.yaml")
+}
+fn is_ruby(path: &Path) -> bool {
+    contains_entry(path, "Gemfile")
+}
+fn is_zig(path: &Path) -> bool {
+    contains_entry(path, "build.zig")
+}
+fn is_swift(path: &Path) -> bool {
+    contains_entry(path, "Package.swift")
+    || contains_entry(path, "Podfile")
+    || contains_file_regex(path, ".xcodeproj")
+}
+fn is_cpp(path: &Path) -> bool {
+    has_cpp_sources(path) || is_cpp_build_dir(path)
+}
+fn has_cpp_sources(path: &Path) -> bool {
+    const CPP_FILES: [&str; 4] = ["CMakeLists.txt", "meson.build", "configure.ac", "Makefile.am"];
+
+    CPP_FILES.iter().any(|file| contains_entry(path, file))
+}
 fn is_misc_project(path: &Path) -> Option<PathBuf> {
     const MISC_DIRS: [&str; 3] = ["bin", "build", "dist"];
 
@@ -109,7 +309,16 @@ fn is_repo(path: &Path) -> bool {
 
 fn contains_entry(path: &Path, entry: &str) -> bool {
     let res = path.join(entry).try_exists();
-    if let Ok(val) = res { val } else { false }
+    res.unwrap_or_default()
+}
+
+/// Whether path contains a file such as foo.cabal, unlike the ~/.cabal directory
+fn contains_file_with_extension(path: &Path, extension: &str) -> bool {
+    let Ok(entries) = path.read_dir() else { return false };
+
+    entries.filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .any(|path| path.extension().is_some_and(|ext| ext == extension) && path.is_file())
 }
 
 fn contains_file_regex(path: &Path, pattern: &str) -> bool {
@@ -120,9 +329,9 @@ fn contains_file_regex(path: &Path, pattern: &str) -> bool {
         if entry.is_err() { continue; }
 
         let entry = entry.unwrap();
-        let file_name = entry.file_name().into_string().unwrap();
+        let file_name = entry.file_name();
 
-        if file_name.ends_with(pattern) { return true; }
+        if file_name.to_string_lossy().ends_with(pattern) { return true; }
     }
     false
 }
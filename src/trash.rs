@@ -0,0 +1,134 @@
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use freedesktop::move_to_trash;
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn move_to_trash(_path: &std::path::Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "moving to the trash is not supported on this platform"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod freedesktop {
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Write};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::symlink;
+    use std::path::{Path, PathBuf};
+    use std::time::SystemTime;
+
+    use crate::config::data_dir;
+    use crate::util::{is_cross_device, SECONDS_PER_DAY};
+
+    /// Moves path into the home trash as described by the freedesktop.org trash specification
+    pub fn move_to_trash(path: &Path) -> io::Result<()> {
+        let path = path.canonicalize()?;
+        let trash_dir = home_trash_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unable to locate home directory"))?;
+        let files_dir = trash_dir.join("files");
+        let info_dir = trash_dir.join("info");
+        fs::create_dir_all(&files_dir)?;
+        fs::create_dir_all(&info_dir)?;
+
+        let (info_path, trashed_path) = create_trash_info(&path, &files_dir, &info_dir)?;
+        let copied = match fs::rename(&path, &trashed_path) {
+            Ok(_) => return Ok(()),
+            Err(e) if is_cross_device(&e) => copy_all(&path, &trashed_path)
+                .inspect_err(|_| { let _ = remove_all(&trashed_path); }),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+        // the trashed copy is kept even if removing the original fails partway, it may be all that's left of it
+        remove_all(&path)
+    }
+
+    fn home_trash_dir() -> Option<PathBuf> {
+        Some(data_dir()?.join("Trash"))
+    }
+
+    /// Claims a unique name in the trash by creating its .trashinfo file,
+    /// returns the paths of the info file and where the trashed entry should be moved to
+    fn create_trash_info(path: &Path, files_dir: &Path, info_dir: &Path) -> io::Result<(PathBuf, PathBuf)> {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let contents = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            url_encode(path),
+            format_timestamp(SystemTime::now())
+        );
+
+        for n in 1.. {
+            let name = if n == 1 { file_name.to_string() } else { format!("{}.{}", file_name, n) };
+            let info_path = info_dir.join(format!("{}.trashinfo", name));
+            let trashed_path = files_dir.join(&name);
+            if trashed_path.symlink_metadata().is_ok() { continue; }
+
+            match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(mut info) => {
+                    info.write_all(contents.as_bytes())?;
+                    return Ok((info_path, trashed_path));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!()
+    }
+
+    fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+        let meta = from.symlink_metadata()?;
+        if meta.is_symlink() {
+            symlink(fs::read_link(from)?, to)
+        } else if meta.is_dir() {
+            fs::create_dir(to)?;
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                copy_all(&entry.path(), &to.join(entry.file_name()))?;
+            }
+            fs::set_permissions(to, meta.permissions())
+        } else {
+            fs::copy(from, to).map(|_| ())
+        }
+    }
+
+    fn remove_all(path: &Path) -> io::Result<()> {
+        if path.symlink_metadata()?.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    fn url_encode(path: &Path) -> String {
+        let mut encoded = String::new();
+        for &byte in path.as_os_str().as_bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+                _ => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        encoded
+    }
+
+    /// Formats time as YYYY-MM-DDThh:mm:ss (in UTC, which avoids needing the local timezone)
+    fn format_timestamp(time: SystemTime) -> String {
+        let secs = time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let (days, day_secs) = (secs / SECONDS_PER_DAY, secs % SECONDS_PER_DAY);
+
+        // days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year, month, day, day_secs / 3600, day_secs % 3600 / 60, day_secs % 60
+        )
+    }
+}
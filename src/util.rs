@@ -0,0 +1,24 @@
+use std::io;
+use std::time::SystemTime;
+
+pub const SECONDS_PER_DAY: u64 = 86400;
+
+/// Seconds since the unix epoch
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Whole days since time, None if time is in the future
+pub fn days_since(time: SystemTime) -> Option<u64> {
+    let time_since = SystemTime::now().duration_since(time).ok()?;
+    Some(time_since.as_secs() / SECONDS_PER_DAY)
+}
+
+/// Whether e is the error returned when renaming across filesystems
+pub fn is_cross_device(e: &io::Error) -> bool {
+    const EXDEV: i32 = 18;
+    const ERROR_NOT_SAME_DEVICE: i32 = 17;
+
+    let code = if cfg!(windows) { ERROR_NOT_SAME_DEVICE } else { EXDEV };
+    e.raw_os_error() == Some(code)
+}
@@ -1,8 +1,10 @@
+use std::fs::File;
 use std::process::Command;
+use std::time::{Duration, SystemTime};
 
 use assert_cmd::prelude::{CommandCargoExt, OutputAssertExt};
-use assert_fs::prelude::{PathChild, FileTouch, PathCreateDir};
-use predicates::prelude::predicate::str;
+use assert_fs::prelude::{PathChild, FileTouch, FileWriteStr, PathAssert, PathCreateDir};
+use predicates::prelude::{predicate::{path, str}, PredicateBooleanExt};
 
 #[test]
 fn path_does_not_exist() -> Result<(), Box<dyn std::error::Error>> {
@@ -55,8 +57,975 @@ fn project_found() -> Result<(), Box<dyn std::error::Error>> {
     
     cmd.assert()
         .success()
-        .stdout(str::is_empty());
+        .stdout(str::contains("test_proj (Misc (bin))"))
+        .stdout(str::contains("Would reclaim 0  B from 1 projects"));
+    test_proj.child("bin").assert(path::exists());
 
     test_dir.close()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn batch_delete() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("package.json").touch()?;
+    test_proj.child("node_modules/dep/index.js").write_str("module.exports = {};")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--yes", test_dir.path().to_str().unwrap()]);
+
+    cmd.assert()
+        .success()
+        .stdout(str::contains("test_proj (Node)"))
+        .stdout(str::contains("Reclaimed 20  B from 1 projects"));
+    test_proj.child("node_modules").assert(path::missing());
+    test_proj.child("package.json").assert(path::exists());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn json_output() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("Cargo.toml").touch()?;
+    test_proj.child("target/debug/app").write_str("binary")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--format", "json", test_dir.path().to_str().unwrap()]);
+
+    let target = test_proj.child("target");
+    cmd.assert()
+        .success()
+        .stdout(str::starts_with("[\n  {"))
+        .stdout(str::contains("\"type\":\"Cargo\""))
+        .stdout(str::contains(format!("\"rm_paths\":[{{\"path\":\"{}\",\"size\":6}}]", target.path().display())))
+        .stdout(str::contains("\"rm_size\":6"))
+        .stdout(str::contains("\"last_modified_days\":0"));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn ndjson_output() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    for name in ["proj_a", "proj_b"] {
+        let test_proj = test_dir.child(name);
+        test_proj.create_dir_all()?;
+        test_proj.child(".git").touch()?;
+        test_proj.child("go.mod").touch()?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--format", "ndjson", test_dir.path().to_str().unwrap()]);
+
+    cmd.assert()
+        .success()
+        .stdout(str::is_match("^(\\{\"path\":.*\"type\":\"Golang\".*\\}\n){2}$")?);
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn dry_run_lists_artifacts() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("mix.exs").touch()?;
+    test_proj.child("_build/app.beam").write_str("1234567890")?;
+    test_proj.child("deps/dep.ex").write_str("12345")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+
+    cmd.assert()
+        .success()
+        .stdout(str::is_match(format!("{}\\s+10  B\n", test_proj.child("_build").path().display()))?)
+        .stdout(str::is_match(format!("{}\\s+5  B\n", test_proj.child("deps").path().display()))?)
+        .stdout(str::contains("Would reclaim 15  B from 1 projects"));
+    test_proj.child("_build").assert(path::exists());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn filter_by_age() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("package.json").touch()?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--newer-than", "1w", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("test_proj (Node)"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "30d", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("No projects found."));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "30x", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("invalid duration unit"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "99999999999999999y", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("is too long"));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn filter_by_size() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let small_proj = test_dir.child("small_proj");
+    small_proj.create_dir_all()?;
+    small_proj.child(".git").touch()?;
+    small_proj.child("composer.json").touch()?;
+    small_proj.child("vendor/lib.php").write_str(&"a".repeat(500))?;
+    let big_proj = test_dir.child("big_proj");
+    big_proj.create_dir_all()?;
+    big_proj.child(".git").touch()?;
+    big_proj.child("composer.json").touch()?;
+    big_proj.child("vendor/lib.php").write_str(&"a".repeat(1500))?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--min-size", "1KB", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("big_proj (Composer)"))
+        .stdout(str::contains("small_proj").not());
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--min-size", "1.5k", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("big_proj (Composer)"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--min-size", "1PB", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("invalid size unit"));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn filter_by_type() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let node_proj = test_dir.child("node_proj");
+    node_proj.create_dir_all()?;
+    node_proj.child("package.json").touch()?;
+    node_proj.child("node_modules/pkg/bin").create_dir_all()?;
+    // not a misc directory, even when node projects are left out
+    node_proj.child("bin/cli.js").touch()?;
+    let misc_proj = test_dir.child("misc_proj");
+    misc_proj.create_dir_all()?;
+    misc_proj.child("dist").create_dir_all()?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--no-vcs", "--type", "node,cargo", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("node_proj (Node)"))
+        .stdout(str::contains("misc_proj").not());
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--no-vcs", "--exclude-type", "node", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("misc_proj (Misc (dist))"))
+        .stdout(str::contains("node_proj").not());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn local_config() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    test_dir.child("polykill/config.toml").write_str(r#"
+        [[project-type]]
+        name = "Bazel"
+        markers = ["MODULE.bazel"]
+        artifacts = ["bazel-out", "bazel-bin"]
+    "#)?;
+    test_dir.child(".polykill.toml").write_str(r#"exclude-paths = ["keep_proj"]"#)?;
+    let bazel_proj = test_dir.child("bazel_proj");
+    bazel_proj.create_dir_all()?;
+    bazel_proj.child(".git").touch()?;
+    bazel_proj.child("MODULE.bazel").touch()?;
+    bazel_proj.child("bazel-out/lib.a").write_str("1234")?;
+    let keep_proj = test_dir.child("keep_proj");
+    keep_proj.create_dir_all()?;
+    keep_proj.child(".git").touch()?;
+    keep_proj.child("package.json").touch()?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", test_dir.path());
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("bazel_proj (Bazel)"))
+        .stdout(str::contains("Would reclaim 4  B from 1 projects"))
+        .stdout(str::contains("keep_proj").not());
+
+    // the local file can't choose what gets removed or remove anything without asking
+    for config in ["yes = true", "[[project-type]]\nname = \"Evil\"\nmarkers = [\"MODULE.bazel\"]\nartifacts = [\"src\"]"] {
+        test_dir.child(".polykill.toml").write_str(config)?;
+        let mut cmd = Command::cargo_bin("polykill")?;
+        cmd.env("XDG_CONFIG_HOME", test_dir.path());
+        cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+        cmd.assert()
+            .code(2)
+            .stderr(str::contains(".polykill.toml").and(str::contains("can only be set in the global config file")));
+    }
+    bazel_proj.child("bazel-out").assert(path::exists());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn global_config() -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = assert_fs::TempDir::new()?;
+    config_dir.child("polykill/config.toml").write_str(r#"
+        type = ["cargo"]
+        dry-run = true
+    "#)?;
+    let test_dir = assert_fs::TempDir::new()?;
+    for (name, marker) in [("cargo_proj", "Cargo.toml"), ("node_proj", "package.json")] {
+        let test_proj = test_dir.child(name);
+        test_proj.create_dir_all()?;
+        test_proj.child(".git").touch()?;
+        test_proj.child(marker).touch()?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args([test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("cargo_proj (Cargo)"))
+        .stdout(str::contains("node_proj").not());
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args(["--type", "node", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("node_proj (Node)"))
+        .stdout(str::contains("cargo_proj").not());
+
+    config_dir.child("polykill/config.toml").write_str("min-size = \"lots\"")?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .code(2)
+        .stderr(str::contains("invalid size"));
+
+    config_dir.child("polykill/config.toml").write_str("[[project-type]]\nname = \"Evil\"\nmarkers = [\"Cargo.toml\"]\nartifacts = [\"../../victim\"]")?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .code(2)
+        .stderr(str::contains("must be a relative path"));
+
+    config_dir.close()?;
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn parallel_search_matches_serial() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    for i in 0..20 {
+        let test_proj = test_dir.child(format!("group_{}/proj_{}", i % 4, i));
+        test_proj.create_dir_all()?;
+        test_proj.child(".git").touch()?;
+        test_proj.child("package.json").touch()?;
+        test_proj.child("node_modules/a/index.js").write_str(&"a".repeat(i * 100))?;
+        test_proj.child("node_modules/b/c/index.js").write_str(&"b".repeat(i * 10))?;
+    }
+
+    let output = |threads: &str| -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("polykill")?;
+        cmd.args(["--format", "json", "--threads", threads, test_dir.path().to_str().unwrap()]);
+        Ok(cmd.assert().success().get_output().stdout.to_owned())
+    };
+    let serial = output("1")?;
+    assert_eq!(serial, output("8")?);
+    assert_eq!(String::from_utf8(serial)?.matches("\"type\":\"Node\"").count(), 20);
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn batch_trash() -> Result<(), Box<dyn std::error::Error>> {
+    let data_dir = assert_fs::TempDir::new()?;
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("package.json").touch()?;
+    test_proj.child("node_modules/dep/index.js").write_str("module.exports = {};")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_DATA_HOME", data_dir.path());
+    cmd.args(["--yes", "--trash", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("Moved to the trash 20  B from 1 projects"));
+
+    test_proj.child("node_modules").assert(path::missing());
+    data_dir.child("Trash/files/node_modules/dep/index.js").assert("module.exports = {};");
+    data_dir.child("Trash/info/node_modules.trashinfo")
+        .assert(str::contains("[Trash Info]\nPath=/").and(str::contains("/test%20proj/node_modules\n")))
+        .assert(str::is_match("\nDeletionDate=\\d{4}-\\d{2}-\\d{2}T\\d{2}:\\d{2}:\\d{2}\n")?);
+
+    data_dir.close()?;
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn quarantine_and_purge() -> Result<(), Box<dyn std::error::Error>> {
+    let data_dir = assert_fs::TempDir::new()?;
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("Cargo.toml").touch()?;
+    test_proj.child("target/debug/app").write_str("binary")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_DATA_HOME", data_dir.path());
+    cmd.args(["--yes", "--quarantine", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("Quarantined 6  B from 1 projects"));
+    test_proj.child("target").assert(path::missing());
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_DATA_HOME", data_dir.path());
+    cmd.args(["purge", "--older-than", "1d"]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("Purged 0  B from 0 deletions"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_DATA_HOME", data_dir.path());
+    cmd.args(["purge"]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("Purged 6  B from 1 deletions"));
+    data_dir.child("polykill/quarantine").assert(predicates::function::function(|dir: &std::path::Path| {
+        dir.read_dir().unwrap().next().is_none()
+    }));
+
+    data_dir.close()?;
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn python_project() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("py_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("pyproject.toml").touch()?;
+    test_proj.child(".venv/pyvenv.cfg").write_str("home = /usr/bin")?;
+    test_proj.child("src/pkg/__pycache__/mod.pyc").write_str("pyc")?;
+    test_proj.child("src/pkg.egg-info/PKG-INFO").write_str("info")?;
+    test_proj.child(".mypy_cache/cache").write_str("cache")?;
+    test_proj.child("build/lib/pkg/__pycache__/mod.pyc").write_str("pyc")?;
+    let venv_only = test_dir.child("scripts");
+    venv_only.create_dir_all()?;
+    venv_only.child(".git").touch()?;
+    venv_only.child("venv/pyvenv.cfg").write_str("home = /usr/bin")?;
+    venv_only.child("venv/lib/pkg/bin").create_dir_all()?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("py_proj (Python)"))
+        .stdout(str::contains(test_proj.child(".venv").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child("src/pkg/__pycache__").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child("src/pkg.egg-info").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child(".mypy_cache").path().to_str().unwrap()))
+        // counted as part of build
+        .stdout(str::contains(test_proj.child("build/lib/pkg/__pycache__").path().to_str().unwrap()).not())
+        .stdout(str::contains("scripts (Python)"))
+        .stdout(str::contains(venv_only.child("venv").path().to_str().unwrap()))
+        .stdout(str::contains("Misc").not());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn maven_multi_module() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let parent = test_dir.child("parent");
+    parent.create_dir_all()?;
+    parent.child(".git").touch()?;
+    parent.child("pom.xml").write_str("<project><modules>\n<module>core</module>\n<!-- <module>old</module> -->\n<module>app</module>\n</modules></project>")?;
+    parent.child("target/classes").create_dir_all()?;
+    parent.child("core/pom.xml").write_str("<project><modules><module>api</module></modules></project>")?;
+    parent.child("core/target/core.jar").write_str("jar")?;
+    parent.child("core/api/pom.xml").write_str("<project></project>")?;
+    parent.child("core/api/target/api.jar").write_str("jar")?;
+    parent.child("app/pom.xml").write_str("<project></project>")?;
+    parent.child("app/target/app.jar").write_str("jar")?;
+    parent.child("old/pom.xml").write_str("<project></project>")?;
+    parent.child("old/target/old.jar").write_str("jar")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("parent (Maven)"))
+        .stdout(str::contains(parent.child("target").path().to_str().unwrap()))
+        .stdout(str::contains(parent.child("core/target").path().to_str().unwrap()))
+        .stdout(str::contains(parent.child("core/api/target").path().to_str().unwrap()))
+        .stdout(str::contains(parent.child("app/target").path().to_str().unwrap()))
+        // commented out modules are only found as nested projects of their own
+        .stdout(str::contains("old (Maven)"))
+        .stdout(str::contains("from 2 projects"));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn cpp_build_dirs() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("cpp_proj");
+    test_proj.create_dir_all()?;
+    test_proj.child("CMakeLists.txt").touch()?;
+    test_proj.child("cmake-build-debug/main.o").write_str("obj")?;
+    test_proj.child("release/CMakeCache.txt").write_str("cache")?;
+    test_proj.child("build/meson/meson-private/coredata.dat").write_str("data")?;
+    test_proj.child("src/main.cpp").write_str("int main() {}")?;
+    // a hand-written build.ninja doesn't make a build directory
+    test_proj.child("tools/build.ninja").write_str("rule cc")?;
+    test_proj.child("tools/tool.c").write_str("int main() {}")?;
+    let hand_written = test_dir.child("hand_written");
+    hand_written.child("build.ninja").write_str("rule cc")?;
+    hand_written.child("tool.c").write_str("int main() {}")?;
+    let out_of_tree = test_dir.child("cpp_proj-ninja");
+    out_of_tree.child("build.ninja").write_str("rule cc")?;
+    out_of_tree.child(".ninja_log").write_str("# ninja log v5")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--no-vcs", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("cpp_proj (Cpp)"))
+        .stdout(str::contains(test_proj.child("cmake-build-debug").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child("release").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child("build/meson").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child("src").path().to_str().unwrap()).not())
+        .stdout(str::contains(test_proj.child("tools").path().to_str().unwrap()).not())
+        .stdout(str::contains("hand_written").not())
+        .stdout(str::contains("cpp_proj-ninja (Cpp)"))
+        .stdout(str::contains("from 2 projects"));
+
+    // in-source builds only have their generated files removed, never the project itself
+    let in_source = test_dir.child("in_source");
+    in_source.child("CMakeLists.txt").touch()?;
+    in_source.child("CMakeCache.txt").write_str("cache")?;
+    in_source.child("CMakeFiles/main.o").write_str("obj")?;
+    in_source.child("src/main.cpp").write_str("int main() {}")?;
+    let node_ninja = test_dir.child("node_ninja");
+    node_ninja.child("package.json").write_str("{}")?;
+    node_ninja.child("build.ninja").write_str("rule cc")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--yes", "--no-vcs", test_dir.path().to_str().unwrap()]);
+    cmd.assert().success();
+    in_source.child("src/main.cpp").assert(path::exists());
+    in_source.child("CMakeLists.txt").assert(path::exists());
+    in_source.child("CMakeCache.txt").assert(path::missing());
+    in_source.child("CMakeFiles").assert(path::missing());
+    node_ninja.child("package.json").assert(path::exists());
+    out_of_tree.assert(path::missing());
+    hand_written.child("tool.c").assert(path::exists());
+    test_proj.child("tools/tool.c").assert(path::exists());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn swift_project() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("ios_app");
+    test_proj.create_dir_all()?;
+    test_proj.child(".git").touch()?;
+    test_proj.child("App.xcodeproj/project.pbxproj").write_str("{}")?;
+    test_proj.child("Pods/Alamofire/Source.swift").write_str("swift")?;
+    test_proj.child("DerivedData/Build/app.o").write_str("obj")?;
+    let package = test_dir.child(".hidden_parent/package");
+    package.create_dir_all()?;
+    package.child(".git").touch()?;
+    package.child("Package.swift").touch()?;
+    package.child(".build/debug/package").write_str("bin")?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("ios_app (Swift)"))
+        .stdout(str::contains(test_proj.child("Pods").path().to_str().unwrap()))
+        .stdout(str::contains(test_proj.child("DerivedData").path().to_str().unwrap()))
+        .stdout(str::contains("package (Swift)").not());
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.child(".hidden_parent").path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("package (Swift)"))
+        .stdout(str::contains(package.child(".build").path().to_str().unwrap()));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn haskell_ocaml_scala_projects() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let haskell = test_dir.child("hs_proj");
+    haskell.child("hs_proj.cabal").write_str("name: hs_proj")?;
+    haskell.child("dist-newstyle/cache/plan.json").write_str("{}")?;
+    let ocaml = test_dir.child("ml_proj");
+    ocaml.child("dune-project").write_str("(lang dune 3.0)")?;
+    ocaml.child("_build/default/main.exe").write_str("exe")?;
+    let scala = test_dir.child("scala_proj");
+    scala.child("build.sbt").touch()?;
+    scala.child("project/target/config-classes").create_dir_all()?;
+    scala.child(".bloop/bloop.settings.json").write_str("{}")?;
+    for proj in [&haskell, &ocaml, &scala] {
+        proj.child(".git").touch()?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("hs_proj (Haskell)"))
+        .stdout(str::contains(haskell.child("dist-newstyle").path().to_str().unwrap()))
+        .stdout(str::contains("ml_proj (Ocaml)"))
+        .stdout(str::contains(ocaml.child("_build").path().to_str().unwrap()))
+        .stdout(str::contains("scala_proj (Scala)"))
+        .stdout(str::contains(scala.child("project/target").path().to_str().unwrap()))
+        .stdout(str::contains(scala.child(".bloop").path().to_str().unwrap()));
+
+    // cabal's own directory doesn't make a home directory a haskell project
+    let home = test_dir.child("home");
+    home.child(".cabal/packages/hackage.haskell.org/01-index.cache").touch()?;
+    home.child("work/tool/bin/run.sh").touch()?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--no-vcs", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("tool (Misc (bin))"))
+        .stdout(str::contains("home (Haskell)").not());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn ruby_flutter_zig_projects() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let ruby = test_dir.child("rb_proj");
+    ruby.child("Gemfile").write_str("source 'https://rubygems.org'")?;
+    ruby.child("vendor/bundle/ruby/3.3.0/gems").create_dir_all()?;
+    ruby.child("vendor/assets/app.js").write_str("js")?;
+    let flutter = test_dir.child("flutter_app");
+    flutter.child("pubspec.yaml").write_str("name: flutter_app")?;
+    flutter.child("build/app.apk").write_str("apk")?;
+    flutter.child("ios/Podfile").touch()?;
+    flutter.child("ios/Pods/Flutter/Flutter.h").write_str("h")?;
+    let zig = test_dir.child("zig_proj");
+    zig.child("build.zig").touch()?;
+    zig.child(".zig-cache/o/main.o").write_str("obj")?;
+    zig.child("zig-out/bin/zig_proj").write_str("bin")?;
+    for proj in [&ruby, &flutter, &zig] {
+        proj.child(".git").touch()?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("rb_proj (Ruby)"))
+        .stdout(str::contains(ruby.child("vendor/bundle").path().to_str().unwrap()))
+        .stdout(str::contains(ruby.child("vendor/assets").path().to_str().unwrap()).not())
+        .stdout(str::contains("flutter_app (Flutter)"))
+        .stdout(str::contains(flutter.child("build").path().to_str().unwrap()))
+        .stdout(str::contains(flutter.child("ios/Pods").path().to_str().unwrap()))
+        .stdout(str::contains("zig_proj (Zig)"))
+        .stdout(str::contains(zig.child(".zig-cache").path().to_str().unwrap()))
+        .stdout(str::contains(zig.child("zig-out").path().to_str().unwrap()));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn gradle_modules() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let android = test_dir.child("android_app");
+    android.child("settings.gradle").write_str("rootProject.name = 'android_app'\ninclude ':app',\n    ':libs:core'\n// include ':old'\n")?;
+    android.child("build.gradle").touch()?;
+    android.child(".gradle/8.0/fileHashes").create_dir_all()?;
+    android.child("app/build/outputs/app.apk").write_str("apk")?;
+    android.child("app/.cxx/Debug/build.ninja").write_str("ninja")?;
+    android.child("libs/core/build/core.jar").write_str("jar")?;
+    android.child("old/build/old.jar").write_str("jar")?;
+    let walked = test_dir.child("walked");
+    walked.child("build.gradle.kts").touch()?;
+    walked.child("server/build.gradle.kts").touch()?;
+    walked.child("server/build/server.jar").write_str("jar")?;
+    for proj in [&android, &walked] {
+        proj.child(".git").touch()?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("android_app (Gradle)"))
+        .stdout(str::contains(android.child(".gradle").path().to_str().unwrap()))
+        .stdout(str::contains(android.child("app/build").path().to_str().unwrap()))
+        .stdout(str::contains(android.child("app/.cxx").path().to_str().unwrap()))
+        .stdout(str::contains(android.child("libs/core/build").path().to_str().unwrap()))
+        .stdout(str::contains(android.child("old/build").path().to_str().unwrap()).not())
+        .stdout(str::contains("walked (Gradle)"))
+        .stdout(str::contains(walked.child("server/build").path().to_str().unwrap()))
+        .stdout(str::contains("from 2 projects"));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn multiple_project_types() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let rails = test_dir.child("rails_app");
+    rails.child("Gemfile").touch()?;
+    rails.child("package.json").write_str("{}")?;
+    rails.child("node_modules/react/index.js").write_str("js")?;
+    rails.child("vendor/bundle/ruby").create_dir_all()?;
+    rails.child("dist/app.js").write_str("js")?;
+    let tauri = test_dir.child("tauri_app");
+    tauri.child("package.json").write_str("{}")?;
+    tauri.child("node_modules/vite/index.js").write_str("js")?;
+    tauri.child("src-tauri/Cargo.toml").touch()?;
+    tauri.child("src-tauri/target/debug/app").write_str("bin")?;
+    for proj in [&rails, &tauri] {
+        proj.child(".git").touch()?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("rails_app (Node+Ruby)"))
+        .stdout(str::contains(rails.child("node_modules").path().to_str().unwrap()))
+        .stdout(str::contains(rails.child("vendor/bundle").path().to_str().unwrap()))
+        .stdout(str::contains(rails.child("dist").path().to_str().unwrap()).not())
+        .stdout(str::contains("tauri_app (Node)"))
+        .stdout(str::contains("src-tauri (Cargo)"))
+        .stdout(str::contains("from 3 projects"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--format", "ndjson", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("\"type\":\"Node\",\"types\":[{\"name\":\"Node\",\"enabled\":true},{\"name\":\"Ruby\",\"enabled\":true}]"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--format", "json", "-t", "cargo", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("src-tauri\",\"type\":\"Cargo\""))
+        .stdout(str::contains("node_modules").not());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn git_tracked_artifacts() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let config_dir = test_dir.child("config");
+    config_dir.create_dir_all()?;
+    let repo = test_dir.child("repo");
+    repo.child("package.json").write_str("{}")?;
+    repo.child(".gitignore").write_str("/node_modules/\n")?;
+    repo.child("node_modules/dep/index.js").write_str("js")?;
+    repo.child("tools/Cargo.toml").touch()?;
+    repo.child("tools/target/debug/tool").write_str("bin")?;
+    let scripts = test_dir.child("scripts");
+    scripts.child("bin/deploy.sh").write_str("#!/bin/sh")?;
+    for (dir, tracked) in [(&repo, "package.json"), (&scripts, "bin")] {
+        let git = |args: &[&str]| Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .env("XDG_CONFIG_HOME", config_dir.path())
+            .env("HOME", config_dir.path())
+            .status();
+        git(&["init", "-q"])?;
+        git(&["add", tracked])?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", config_dir.path()).env("HOME", config_dir.path());
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains(format!("{} (tracked!)", scripts.child("bin").path().display())))
+        .stdout(str::contains(format!("{} (not ignored)", repo.child("tools/target").path().display())))
+        .stdout(str::contains(format!("{}  ", repo.child("node_modules").path().display())));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("XDG_CONFIG_HOME", config_dir.path()).env("HOME", config_dir.path());
+    cmd.args(["--yes", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stderr(str::contains("contains files tracked by git"))
+        .stderr(str::contains("is not ignored by git"));
+    repo.child("node_modules").assert(path::missing());
+    repo.child("tools/target").assert(path::exists());
+    scripts.child("bin/deploy.sh").assert(path::exists());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn git_status() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let config_dir = test_dir.child("config");
+    config_dir.create_dir_all()?;
+    let repo = test_dir.child("repo");
+    repo.child("Cargo.toml").write_str("[package]")?;
+    repo.child("target/debug/app").write_str("bin")?;
+    repo.child("src/main.rs").write_str("fn main() {}")?;
+    let git = |args: &[&str]| Command::new("git")
+        .args(args)
+        .current_dir(repo.path())
+        .env("HOME", config_dir.path())
+        .env("XDG_CONFIG_HOME", config_dir.path())
+        .env("GIT_AUTHOR_NAME", "test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .status();
+    git(&["init", "-q"])?;
+    git(&["symbolic-ref", "HEAD", "refs/heads/feature"])?;
+    git(&["add", "Cargo.toml", "src/main.rs"])?;
+    git(&["commit", "-q", "-m", "initial"])?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("HOME", config_dir.path()).env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args(["--format", "ndjson", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("\"git\":{\"branch\":\"feature\",\"last_commit_days\":0,\"dirty\":false,\"unpushed\":null}"));
+
+    repo.child("new.txt").touch()?;
+    git(&["add", "new.txt"])?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("HOME", config_dir.path()).env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args(["--format", "ndjson", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("\"dirty\":true"));
+
+    git(&["update-ref", "refs/remotes/origin/feature", "HEAD"])?;
+    git(&["commit", "-q", "-m", "add new.txt"])?;
+    repo.child("Cargo.toml").write_str("[package]\nname = \"app\"")?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.env("HOME", config_dir.path()).env("XDG_CONFIG_HOME", config_dir.path());
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("repo (Cargo) [feature, last commit 0 days ago, dirty, 1 unpushed]"));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn age_source() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let test_proj = test_dir.child("test_proj");
+    test_proj.child(".git").touch()?;
+    test_proj.child("Cargo.toml").touch()?;
+    test_proj.child("src/main.rs").touch()?;
+    test_proj.child("target/debug/app").touch()?;
+    // artifacts of other types and nested projects don't count as source
+    test_proj.child("docs/node_modules/dep/index.js").touch()?;
+    test_proj.child("tools/web/package.json").touch()?;
+    test_proj.child("tools/web/node_modules/dep/index.js").touch()?;
+    let long_ago = SystemTime::now() - Duration::from_secs(100 * 86400);
+    for file in [".git", "Cargo.toml", "src/main.rs", "src"] {
+        File::open(test_proj.child(file).path())?.set_modified(long_ago)?;
+    }
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "30d", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("test_proj (Cargo)"));
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "30d", "--age-source", "dir", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("No projects found."));
+
+    // not a git repository, so the commit source falls back to the source files
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "30d", "--age-source", "commit", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("test_proj (Cargo)"));
+
+    test_proj.child("src/lib.rs").touch()?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", "--older-than", "30d", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("No projects found."));
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn nested_projects() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let repo = test_dir.child("monorepo");
+    repo.child(".git").touch()?;
+    repo.child("package.json").touch()?;
+    repo.child("node_modules/dep/package.json").touch()?;
+    repo.child("services/api/Cargo.toml").touch()?;
+    repo.child("services/api/target/debug/api").write_str("bin")?;
+    repo.child("services/api/src/bin/tool.rs").touch()?;
+    repo.child("services/worker/go.mod").touch()?;
+    repo.child("web/app/package.json").touch()?;
+    repo.child("web/app/node_modules/dep/package.json").touch()?;
+    repo.child("docs/requirements.txt").touch()?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("monorepo (Node)"))
+        .stdout(str::contains("api (Cargo)"))
+        .stdout(str::contains("app (Node)"))
+        // nested projects without artifacts and misc directories inside projects are left out
+        .stdout(str::contains("worker").not())
+        .stdout(str::contains("docs").not())
+        .stdout(str::contains("src (Misc").not())
+        .stdout(str::contains("dep (Node)").not())
+        .stdout(str::contains("from 3 projects"));
+
+    // without a project at the root, deep projects are still found but misc directories only near the root
+    let plain_repo = test_dir.child("plain_repo");
+    plain_repo.child(".git").touch()?;
+    plain_repo.child("libs/core/parser/Cargo.toml").touch()?;
+    plain_repo.child("libs/core/parser/target/debug/parser").write_str("bin")?;
+    plain_repo.child("tools/scripts/bin/deploy.sh").touch()?;
+    plain_repo.child("docs/guides/tools/bin/build.sh").touch()?;
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::contains("parser (Cargo)"))
+        .stdout(str::contains("scripts (Misc (bin))"))
+        .stdout(str::contains("tools (Misc").not());
+
+    test_dir.close()?;
+    Ok(())
+}
+
+#[test]
+fn workspaces() -> Result<(), Box<dyn std::error::Error>> {
+    let test_dir = assert_fs::TempDir::new()?;
+    let cargo_ws = test_dir.child("cargo_ws");
+    cargo_ws.child(".git").touch()?;
+    cargo_ws.child("Cargo.toml").write_str("[workspace]\nmembers = [\"crates/*\", \"cli\"]\nexclude = [\"crates/skipped\"]")?;
+    cargo_ws.child("target/debug/cli").write_str("bin")?;
+    cargo_ws.child("crates/core/Cargo.toml").touch()?;
+    cargo_ws.child("crates/core/target/debug/core").write_str("bin")?;
+    cargo_ws.child("crates/skipped/Cargo.toml").touch()?;
+    cargo_ws.child("cli/Cargo.toml").touch()?;
+
+    let npm_ws = test_dir.child("npm_ws");
+    npm_ws.child(".git").touch()?;
+    npm_ws.child("package.json").write_str(r#"{"name": "root", "workspaces": ["packages/*"]}"#)?;
+    npm_ws.child("node_modules/dep/index.js").write_str("dep")?;
+    npm_ws.child("packages/ui/package.json").touch()?;
+    npm_ws.child("packages/ui/node_modules/dep/index.js").write_str("ui dep")?;
+
+    let pnpm_ws = test_dir.child("pnpm_ws");
+    pnpm_ws.child(".git").touch()?;
+    pnpm_ws.child("package.json").write_str("{}")?;
+    pnpm_ws.child("pnpm-workspace.yaml").write_str("packages:\n  - 'apps/**'\n  - '!apps/legacy'\n")?;
+    pnpm_ws.child("node_modules/.pnpm/dep").create_dir_all()?;
+    pnpm_ws.child("apps/web/package.json").touch()?;
+    pnpm_ws.child("apps/web/node_modules/dep").create_dir_all()?;
+    pnpm_ws.child("apps/legacy/package.json").touch()?;
+    pnpm_ws.child("apps/legacy/node_modules/dep").create_dir_all()?;
+
+    let mut cmd = Command::cargo_bin("polykill")?;
+    cmd.args(["--dry-run", test_dir.path().to_str().unwrap()]);
+    cmd.assert()
+        .success()
+        .stdout(str::is_match(r"cargo_ws \(Cargo\) +6  B")?)
+        .stdout(str::contains(cargo_ws.child("crates/core/target").path().to_str().unwrap()))
+        .stdout(str::is_match(r"npm_ws \(Node\) +9  B")?)
+        .stdout(str::contains(npm_ws.child("packages/ui/node_modules").path().to_str().unwrap()))
+        .stdout(str::contains(pnpm_ws.child("apps/web/node_modules").path().to_str().unwrap()))
+        // excluded members are found as nested projects of their own
+        .stdout(str::contains("legacy (Node)"))
+        .stdout(str::contains("core (Cargo)").not())
+        .stdout(str::contains("ui (Node)").not())
+        .stdout(str::contains("from 4 projects"));
+
+    test_dir.close()?;
+    Ok(())
+}